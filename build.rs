@@ -0,0 +1,137 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+//! Generates the signal ID constants and enums in `protocol::ids` from `protocol/ll-id.txt` and `protocol/pk-id.txt`
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+struct Entry {
+    name: String,
+    id: u16,
+}
+
+/// Parses an ID table, whose first column is the name and last column is the hex ID.
+/// `ll-id.txt` also has a binary column, which must match the hex ID.
+fn parse_table(file: &str, prefix: &str, max: u16) -> Vec<Entry> {
+    println!("cargo:rerun-if-changed={}", file);
+    let text = fs::read_to_string(file).unwrap_or_else(|err| panic!("Cannot read {}: {}", file, err));
+    let mut entries: Vec<Entry> = Vec::new();
+    for (number, line) in text.lines().enumerate().skip(1) {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.is_empty() { continue; }
+        let fail = |message: &str| -> ! { panic!("{}:{}: {}", file, number + 1, message) };
+        if columns.len() < 2 {
+            fail("Expected a name and an ID");
+        }
+        let name = columns[0];
+        if !name.starts_with(prefix) || !name[prefix.len()..].chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') {
+            fail(&format!("{} must be an upper case name starting with {}", name, prefix));
+        }
+        let id = match u16::from_str_radix(columns[columns.len() - 1], 16) {
+            Ok(id) if id <= max => id,
+            _ => fail(&format!("Invalid ID for {}", name)),
+        };
+        if columns.len() == 3 && u16::from_str_radix(columns[1], 2) != Ok(id) {
+            fail(&format!("The binary and hex IDs of {} are different", name));
+        }
+        if let Some(other) = entries.iter().find(|entry| entry.name == name || entry.id == id) {
+            fail(&format!("{} conflicts with {}", name, other.name));
+        }
+        entries.push(Entry { name: name.to_owned(), id });
+    }
+    entries
+}
+
+/// `LL_LOGIN_REQUEST` => `LoginRequest`
+fn variant(prefix: &str, name: &str) -> String {
+    name[prefix.len()..].split('_')
+        .map(|word| word[..1].to_owned() + &word[1..].to_ascii_lowercase())
+        .collect()
+}
+
+fn generate(out: &mut String, file: &str, entries: &[Entry], prefix: &str, enum_name: &str, kind: &str, int: &str) {
+    let width = if int == "u8" { 2 } else { 4 };
+    for entry in entries {
+        writeln!(out, "/// The ID of {} in `protocol/{}`", entry.name, file).unwrap();
+        writeln!(out, "pub const {}: {} = 0x{:0width$x};", entry.name, int, entry.id, width = width).unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "/// The {} signal IDs in `protocol/{}`", kind, file).unwrap();
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]").unwrap();
+    writeln!(out, "pub enum {} {{", enum_name).unwrap();
+    for entry in entries {
+        writeln!(out, "    {},", variant(prefix, &entry.name)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "impl {} {{", enum_name).unwrap();
+    writeln!(out, "    pub const ALL: &'static [{}] = &[", enum_name).unwrap();
+    for entry in entries {
+        writeln!(out, "        {}::{},", enum_name, variant(prefix, &entry.name)).unwrap();
+    }
+    writeln!(out, "    ];").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    pub fn from_id(id: {}) -> Option<{}> {{", int, enum_name).unwrap();
+    writeln!(out, "        match id {{").unwrap();
+    for entry in entries {
+        writeln!(out, "            {} => Some({}::{}),", entry.name, enum_name, variant(prefix, &entry.name)).unwrap();
+    }
+    writeln!(out, "            _ => None,").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    pub fn from_name(name: &str) -> Option<{}> {{", enum_name).unwrap();
+    writeln!(out, "        {}::ALL.iter().copied().find(|id| id.name() == name)", enum_name).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    pub fn id(self) -> {} {{", int).unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for entry in entries {
+        writeln!(out, "            {}::{} => {},", enum_name, variant(prefix, &entry.name), entry.name).unwrap();
+    }
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    /// The name in `protocol/{}`", file).unwrap();
+    writeln!(out, "    pub fn name(self) -> &'static str {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for entry in entries {
+        writeln!(out, "            {}::{} => \"{}\",", enum_name, variant(prefix, &entry.name), entry.name).unwrap();
+    }
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+fn main() {
+    let ll = parse_table("protocol/ll-id.txt", "LL_", 0xff);
+    let pk = parse_table("protocol/pk-id.txt", "PK_", 0xffff);
+
+    let mut out = String::new();
+    generate(&mut out, "ll-id.txt", &ll, "LL_", "LowLevelId", "low-level", "u8");
+    generate(&mut out, "pk-id.txt", &pk, "PK_", "PackedId", "packed", "u16");
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("ids.rs");
+    fs::write(path, out).expect("Cannot write the generated IDs");
+}
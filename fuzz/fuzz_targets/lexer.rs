@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cube_engine::conformance::{lex_step_line, parse_step};
+
+fuzz_target!(|line: &str| {
+    let mut tokens = Vec::new();
+    if lex_step_line(line, &mut tokens).is_ok() {
+        let _ = parse_step(&tokens);
+    }
+});
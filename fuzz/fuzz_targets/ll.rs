@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cube_engine::conformance::disassemble;
+use cube_engine::io::reader::CubeReader;
+use cube_engine::protocol::handle_frame;
+use cube_engine::protocol::ll::handle_ll;
+use cube_engine_fuzz::Sink;
+
+fuzz_target!(|data: &[u8]| {
+    // Frames are decoded from a buffer, but `handle_ll` also supports streaming readers
+    let _ = handle_frame(&mut Sink, data);
+    let _ = handle_ll(&mut Sink, &mut CubeReader::new(data));
+    let _ = disassemble(data);
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cube_engine::io::reader::CubeReader;
+use cube_engine::protocol::ll::package::{handle_package, handle_package_slice};
+use cube_engine_fuzz::Sink;
+
+// The input is an LL_PACKAGE without its ID, i.e. the length and the deflated PKs
+fuzz_target!(|data: &[u8]| {
+    let _ = handle_package_slice(&mut Sink, &mut CubeReader::new(data));
+    let _ = handle_package(&mut Sink, &mut CubeReader::new(data));
+});
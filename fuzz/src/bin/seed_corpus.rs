@@ -0,0 +1,102 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+//! Writes the seed corpus of each fuzz target from the conversations in `tests/hex`.
+//!
+//! Run with `cargo run --bin seed_corpus` from the `fuzz` directory.
+
+use std::fs;
+use std::path::Path;
+
+use cube_engine::conformance::{lex_step_line, parse_step, preprocess_file, read_conversations};
+use cube_engine::io::writer::CubeWriter;
+use cube_engine::protocol::ll::package::{LL_PACKAGE, MAX_PACKAGE_INFLATED, PackageWriter};
+use cube_engine::protocol::pk::user_flags::UserFlagsSignal;
+
+fn write(target: &str, name: &str, data: &[u8]) {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir).expect("Failed to create corpus directory");
+    fs::write(dir.join(name), data).expect("Failed to write corpus entry");
+}
+
+/// Packages for the PKs that the conversations in `tests/hex` do not cover yet
+const PACKAGES: &[(&str, &str)] = &[
+    ("spawn", "bits{1} nop PK_SPAWN_SPAWN i32{0} i32{1} i32{0} F{8} F{0.5} F{8} F{0} F{0}"),
+    ("cube-dict", "bits{1} nop PK_LOAD_CUBE_DICT u32{2} u32{0} str{CubePump.Air} u32{1} str{CubePump.Stone}"),
+    ("flex-motion", "bits{1} nop PK_GP_FLEX_MOTION u64{1000} i32{0} i32{1} i32{0} F{8} F{0.5} F{8} F{1.5} F{-0.5} F{0} F{0} F{1}"),
+];
+
+fn package(name: &str, content: &str) {
+    let mut tokens = Vec::new();
+    lex_step_line(format!("Z{{ {} bits{{0}} nop }}", content).as_str(), &mut tokens).expect("Invalid package seed");
+    let deflated = parse_step(&tokens).expect("Invalid package seed");
+    let mut data = (deflated.len() as u32).to_be_bytes().to_vec();
+    data.extend(deflated);
+    write("package", name, &data);
+    data.insert(0, LL_PACKAGE);
+    write("ll", name, &data);
+}
+
+/// A package of USER_FLAGS signals that inflates past `MAX_PACKAGE_INFLATED`
+fn package_bomb() {
+    let signal = UserFlagsSignal { fly_up: false, fly_down: false, free_fly: false, float: false, crouch: false };
+    let mut package = PackageWriter::new();
+    for _ in 0..MAX_PACKAGE_INFLATED / 4 + 1 {
+        package.write(|writer| signal.write(writer)).expect("Failed to write package bomb");
+    }
+    let mut writer = CubeWriter::new(Vec::new());
+    package.flush(&mut writer).expect("Failed to write package bomb");
+    write("ll", "package-bomb", &writer.target);
+    write("package", "package-bomb", &writer.target[1..]);
+}
+
+fn main() {
+    for &(name, content) in PACKAGES {
+        package(name, content);
+    }
+    let batch = format!("bits{{1}} nop PK_LOAD_CUBE_BATCH i32{{0}} i32{{0}} i32{{0}}{}", " 00000001".repeat(4096));
+    package("cube-batch", &batch);
+    package_bomb();
+
+    let hex = Path::new("../tests/hex");
+    for conversation in read_conversations(hex).expect("Failed to read tests/hex") {
+        for (i, step) in conversation.steps.iter().enumerate() {
+            let name = format!("{}-{}", conversation.name, i);
+            write("ll", &name, &step.buffer);
+            if step.buffer.first() == Some(&LL_PACKAGE) {
+                write("package", &name, &step.buffer[1..]);
+            }
+        }
+    }
+
+    let mut files: Vec<_> = fs::read_dir(hex).expect("Failed to read tests/hex")
+        .chain(fs::read_dir(hex.join("lib")).expect("Failed to read tests/hex/lib"))
+        .map(|entry| entry.expect("Failed to read tests/hex").path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+        .collect();
+    files.sort();
+    for file in files {
+        let stem = file.file_stem().unwrap().to_string_lossy().into_owned();
+        let lines = preprocess_file(&file, &hex.join("lib")).expect("Failed to preprocess conversation");
+        for (i, line) in lines.iter().enumerate() {
+            write("lexer", &format!("{}-{}", stem, i), line.trim_start_matches(|c| c == '<' || c == '>').as_bytes());
+        }
+    }
+}
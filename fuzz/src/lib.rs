@@ -0,0 +1,72 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+//! Shared code for the fuzz targets
+
+use cube_engine::protocol::handler::SignalHandler;
+use cube_engine::protocol::ll::auth::{AuthChallenge, AuthResponse};
+use cube_engine::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect, ServerDisconnectReason};
+use cube_engine::protocol::ll::login_accept::LoginAccept;
+use cube_engine::protocol::ll::login_request::LoginRequest;
+use cube_engine::protocol::ll::ping::{Ping, Pong};
+use cube_engine::protocol::pk::cube_batch::{CubeBatchSignal, CubeBatchSignalRef};
+use cube_engine::protocol::pk::cube_dict::CubeDictSignal;
+use cube_engine::protocol::pk::flex_motion::FlexMotionSignal;
+use cube_engine::protocol::pk::cube_update::CubeUpdateSignal;
+use cube_engine::protocol::pk::cube_interact::CubeInteractSignal;
+use cube_engine::protocol::pk::user_motion::UserMotionSignal;
+use cube_engine::protocol::pk::user_flags::UserFlagsSignal;
+use cube_engine::protocol::pk::flex_flags::FlexFlagsSignal;
+use cube_engine::protocol::pk::world_switch::WorldSwitchSignal;
+use cube_engine::protocol::pk::spawn::SpawnSignal;
+use cube_engine::util::VioResult;
+
+/// Accepts every signal, so that fuzzing only exercises the decoders
+pub struct Sink;
+
+impl SignalHandler for Sink {
+    fn handle_ll_login_request(&mut self, _: LoginRequest) -> VioResult { Ok(()) }
+    fn handle_ll_auth_challenge(&mut self, _: AuthChallenge) -> VioResult { Ok(()) }
+    fn handle_ll_auth_response(&mut self, _: AuthResponse) -> VioResult { Ok(()) }
+    fn handle_ll_login_accept(&mut self, _: LoginAccept) -> VioResult { Ok(()) }
+    fn handle_ll_server_disconnect(&mut self, _: ServerDisconnect) -> VioResult { Ok(()) }
+    fn handle_ll_client_disconnect(&mut self, _: ClientDisconnect) -> VioResult { Ok(()) }
+    fn handle_ll_server_disconnect_reason(&mut self, _: ServerDisconnectReason) -> VioResult { Ok(()) }
+    fn handle_ll_ping(&mut self, _: Ping) -> VioResult { Ok(()) }
+    fn handle_ll_pong(&mut self, _: Pong) -> VioResult { Ok(()) }
+    fn handle_pk_spawn(&mut self, _: SpawnSignal) -> VioResult { Ok(()) }
+    fn handle_pk_cube_batch(&mut self, _: CubeBatchSignal) -> VioResult { Ok(()) }
+
+    fn handle_pk_cube_batch_ref(&mut self, signal: CubeBatchSignalRef) -> VioResult {
+        for index in 0..4096 {
+            signal.cube(index);
+        }
+        Ok(())
+    }
+
+    fn handle_pk_cube_dict(&mut self, _: CubeDictSignal) -> VioResult { Ok(()) }
+    fn handle_pk_flex_motion(&mut self, _: FlexMotionSignal) -> VioResult { Ok(()) }
+    fn handle_pk_cube_update(&mut self, _: CubeUpdateSignal) -> VioResult { Ok(()) }
+    fn handle_pk_cube_interact(&mut self, _: CubeInteractSignal) -> VioResult { Ok(()) }
+    fn handle_pk_user_motion(&mut self, _: UserMotionSignal) -> VioResult { Ok(()) }
+    fn handle_pk_user_flags(&mut self, _: UserFlagsSignal) -> VioResult { Ok(()) }
+    fn handle_pk_flex_flags(&mut self, _: FlexFlagsSignal) -> VioResult { Ok(()) }
+    fn handle_pk_world_switch(&mut self, _: WorldSwitchSignal) -> VioResult { Ok(()) }
+}
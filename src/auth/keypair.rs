@@ -0,0 +1,101 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::convert::TryInto;
+use std::fmt;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha1::{Digest, Sha1};
+
+use crate::auth::{AuthError, Authenticator, Credentials};
+use crate::protocol::ll::auth::AuthResponse;
+use crate::util::{make_io_error, IoResult};
+
+/// Prepended to the nonce before signing it, so that a server cannot make the client sign
+/// arbitrary messages with its identity key, e.g. a challenge of another protocol
+pub const AUTH_CONTEXT: &[u8] = b"cube-engine auth v1\0";
+
+/// The message signed in AUTH_RESPONSE, i.e. `AUTH_CONTEXT` followed by the nonce
+pub fn signed_message(nonce: &[u8]) -> Vec<u8> {
+    let mut message = AUTH_CONTEXT.to_vec();
+    message.extend_from_slice(nonce);
+    message
+}
+
+/// The user ID of an Ed25519 public key
+pub fn user_id(public_key: &[u8; 32]) -> [u8; 20] {
+    Sha1::digest(public_key).into()
+}
+
+/// An Ed25519 key pair stored locally by the client
+pub struct Keypair {
+    key: SigningKey,
+}
+
+impl Keypair {
+    /// Generates a key pair from the random number generator of the operating system
+    pub fn generate() -> IoResult<Keypair> {
+        let mut secret = [0; 32];
+        getrandom::getrandom(&mut secret).map_err(|err| make_io_error(&err.to_string()))?;
+        Result::Ok(Keypair::from_secret(&secret))
+    }
+
+    /// Restores a key pair from the secret returned by `secret`
+    pub fn from_secret(secret: &[u8; 32]) -> Keypair {
+        Keypair { key: SigningKey::from_bytes(secret) }
+    }
+
+    pub fn secret(&self) -> [u8; 32] { self.key.to_bytes() }
+
+    pub fn public_key(&self) -> [u8; 32] { self.key.verifying_key().to_bytes() }
+
+    /// The user ID to send in LOGIN_REQUEST
+    pub fn user_id(&self) -> [u8; 20] { user_id(&self.public_key()) }
+}
+
+impl Credentials for Keypair {
+    fn respond(&self, nonce: &[u8]) -> IoResult<AuthResponse> {
+        Result::Ok(AuthResponse {
+            public_key: self.public_key().to_vec(),
+            signature: self.key.sign(&signed_message(nonce)).to_bytes().to_vec(),
+        })
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // never print the secret
+        f.debug_struct("Keypair").field("public_key", &self.public_key()).finish()
+    }
+}
+
+/// Accepts the clients that sign the `signed_message` of the nonce with a key whose SHA-1 hash is their user ID
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeypairAuthenticator;
+
+impl Authenticator for KeypairAuthenticator {
+    fn verify(&self, user_id: &[u8; 20], nonce: &[u8], response: &AuthResponse) -> Result<(), AuthError> {
+        let public_key: &[u8; 32] = response.public_key.as_slice().try_into().map_err(|_| AuthError::Malformed)?;
+        if self::user_id(public_key) != *user_id {
+            return Result::Err(AuthError::KeyMismatch);
+        }
+        let key = VerifyingKey::from_bytes(public_key).map_err(|_| AuthError::Malformed)?;
+        let signature = Signature::from_slice(&response.signature).map_err(|_| AuthError::Malformed)?;
+        key.verify_strict(&signed_message(nonce), &signature).map_err(|_| AuthError::BadSignature)
+    }
+}
@@ -0,0 +1,78 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::auth::{AuthError, Authenticator, Credentials, Keypair, KeypairAuthenticator};
+use crate::protocol::ll::auth::AuthResponse;
+
+/// The secret key of RFC 8032, section 7.1, test 1
+const SECRET: [u8; 32] = [
+    0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+    0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+];
+
+#[test]
+fn known_answer() {
+    let keypair = Keypair::from_secret(&SECRET);
+    assert_eq!(keypair.public_key()[..4], [0xd7, 0x5a, 0x98, 0x01]);
+    assert_eq!(keypair.user_id(), [
+        0x5b, 0x27, 0xaa, 0x55, 0x89, 0x17, 0x97, 0x70, 0xe4, 0x75,
+        0x75, 0xb1, 0x62, 0xa1, 0xde, 0xd9, 0x7b, 0x8b, 0xfc, 0x6d,
+    ]);
+    let response = keypair.respond(&[]).unwrap();
+    assert_eq!(response.public_key, keypair.public_key().to_vec());
+    assert_eq!(response.signature[..4], [0x8b, 0xb4, 0xfa, 0xb0]);
+    assert_eq!(response.signature[60..], [0xb9, 0x25, 0x07, 0x09]);
+}
+
+#[test]
+fn verify() {
+    let keypair = Keypair::generate().unwrap();
+    assert_eq!(Keypair::from_secret(&keypair.secret()).public_key(), keypair.public_key());
+
+    let nonce = [1, 2, 3, 4];
+    let response = keypair.respond(&nonce).unwrap();
+    assert_eq!(KeypairAuthenticator.verify(&keypair.user_id(), &nonce, &response), Ok(()));
+    assert_eq!(KeypairAuthenticator.verify(&keypair.user_id(), &[1, 2, 3], &response), Err(AuthError::BadSignature));
+
+    let other = Keypair::from_secret(&SECRET);
+    assert_eq!(KeypairAuthenticator.verify(&other.user_id(), &nonce, &response), Err(AuthError::KeyMismatch));
+}
+
+#[test]
+fn verify_malformed() {
+    let keypair = Keypair::from_secret(&SECRET);
+    let response = keypair.respond(&[]).unwrap();
+    let short_key = AuthResponse { public_key: response.public_key[1..].to_vec(), signature: response.signature.clone() };
+    assert_eq!(KeypairAuthenticator.verify(&keypair.user_id(), &[], &short_key), Err(AuthError::Malformed));
+    let short_signature = AuthResponse { public_key: response.public_key.clone(), signature: response.signature[1..].to_vec() };
+    assert_eq!(KeypairAuthenticator.verify(&keypair.user_id(), &[], &short_signature), Err(AuthError::Malformed));
+}
+
+#[test]
+fn random_nonce() {
+    let nonce = KeypairAuthenticator.nonce().unwrap();
+    assert_eq!(nonce.len(), crate::auth::NONCE_SIZE);
+    assert_ne!(nonce, KeypairAuthenticator.nonce().unwrap());
+}
+
+#[test]
+fn debug_hides_secret() {
+    let debug = format!("{:?}", Keypair::from_secret(&SECRET));
+    assert!(debug.contains("public_key"));
+    assert!(!debug.contains("157, 97, 177"));
+}
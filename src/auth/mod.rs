@@ -0,0 +1,95 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! Proof of ownership of the user ID in LOGIN_REQUEST.
+//!
+//! If a server session has an `Authenticator`, it sends AUTH_CHALLENGE with a nonce
+//! after LOGIN_REQUEST, and the client answers with the AUTH_RESPONSE produced by its `Credentials`.
+//! `keypair` implements both sides with Ed25519 keys whose SHA-1 hash is the user ID.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::protocol::ll::auth::AuthResponse;
+use crate::text::Text;
+use crate::util::{make_io_error, IoResult};
+
+pub use self::keypair::{Keypair, KeypairAuthenticator};
+
+pub mod keypair;
+#[cfg(test)]
+mod keypair_test;
+
+/// Verifies AUTH_RESPONSE on the server side
+pub trait Authenticator: Send + Sync {
+    /// Generates the nonce sent in AUTH_CHALLENGE
+    fn nonce(&self) -> IoResult<Vec<u8>> {
+        random_nonce()
+    }
+
+    /// Checks that `response` proves the ownership of `user_id` by answering `nonce`
+    fn verify(&self, user_id: &[u8; 20], nonce: &[u8], response: &AuthResponse) -> Result<(), AuthError>;
+}
+
+/// Answers AUTH_CHALLENGE on the client side
+pub trait Credentials {
+    fn respond(&self, nonce: &[u8]) -> IoResult<AuthResponse>;
+}
+
+/// The reason why an AUTH_RESPONSE is rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthError {
+    /// The public key or the signature cannot be decoded
+    Malformed,
+    /// The public key does not belong to the user ID
+    KeyMismatch,
+    /// The signature does not sign the nonce
+    BadSignature,
+    /// Rejected by the authenticator for another reason
+    Other(String),
+}
+
+impl AuthError {
+    /// The message shown to the player, see `server::catalog` for the keys
+    pub fn text(&self) -> Text {
+        match self {
+            AuthError::Malformed => Text::translatable("auth.malformed", Vec::new(), "Malformed authentication response"),
+            AuthError::KeyMismatch => Text::translatable("auth.keyMismatch", Vec::new(), "The public key does not match the user ID"),
+            AuthError::BadSignature => Text::translatable("auth.badSignature", Vec::new(), "Invalid signature"),
+            AuthError::Other(reason) => Text::literal(reason),
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl Error for AuthError {}
+
+/// The number of random bytes in the default nonce
+pub const NONCE_SIZE: usize = 32;
+
+/// Generates `NONCE_SIZE` bytes from the random number generator of the operating system
+pub fn random_nonce() -> IoResult<Vec<u8>> {
+    let mut nonce = vec![0; NONCE_SIZE];
+    getrandom::getrandom(&mut nonce).map_err(|err| make_io_error(&err.to_string()))?;
+    Result::Ok(nonce)
+}
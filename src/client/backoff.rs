@@ -0,0 +1,46 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::Duration;
+
+/// The delays between reconnection attempts, doubling from `initial` up to `max`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    /// The number of attempts before giving up
+    pub max_attempts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Backoff {
+        Backoff { initial: Duration::from_secs(1), max: Duration::from_secs(30), max_attempts: 8 }
+    }
+}
+
+impl Backoff {
+    /// The delay before the attempt after `attempts` failed attempts, or `None` to give up
+    pub fn delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        let factor = 1u32.checked_shl(attempts).unwrap_or(u32::MAX);
+        Some(self.initial.checked_mul(factor).map_or(self.max, |delay| delay.min(self.max)))
+    }
+}
@@ -0,0 +1,42 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::time::Duration;
+
+use crate::client::backoff::Backoff;
+
+#[test]
+fn delay() {
+    let backoff = Backoff { initial: Duration::from_millis(500), max: Duration::from_secs(3), max_attempts: 5 };
+    let delays: Vec<Option<Duration>> = (0..6).map(|attempts| backoff.delay(attempts)).collect();
+    assert_eq!(delays, [
+        Some(Duration::from_millis(500)),
+        Some(Duration::from_secs(1)),
+        Some(Duration::from_secs(2)),
+        Some(Duration::from_secs(3)),
+        Some(Duration::from_secs(3)),
+        None,
+    ]);
+}
+
+#[test]
+fn delay_overflow() {
+    let backoff = Backoff { max_attempts: u32::MAX, ..Backoff::default() };
+    assert_eq!(backoff.delay(40), Some(backoff.max));
+    assert_eq!(backoff.delay(u32::MAX - 1), Some(backoff.max));
+}
@@ -17,54 +17,169 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::client::{Client, ClientAdapter};
+use crate::client::{Client, ClientAdapter, ClientState};
 use crate::protocol::handler::SignalHandler;
-use crate::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect};
+use crate::protocol::ll::auth::{AuthChallenge, AuthResponse};
+use crate::protocol::ll::disconnect::{ClientDisconnect, DisconnectCode, ServerDisconnect, ServerDisconnectReason};
 use crate::protocol::ll::login_accept::LoginAccept;
 use crate::protocol::ll::login_request::LoginRequest;
 use crate::protocol::ll::ping::{Ping, Pong};
 use crate::protocol::pk::spawn::SpawnSignal;
 use crate::protocol::pk::cube_dict::CubeDictSignal;
 use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::version::ProtocolVersion;
+use crate::util::{io_error, VioResult};
 
 macro_rules! cs_only {
     () => (
-        unimplemented!()
+        io_error("Received a client-to-server signal from the server")
     )
 }
 
 #[allow(unused_variables)]
 impl<A: ClientAdapter> SignalHandler for Client<A> {
-    fn handle_ll_login_request(&mut self, signal: LoginRequest) { cs_only!() }
+    fn version(&self) -> ProtocolVersion {
+        self.version.unwrap_or(self.config.version)
+    }
 
-    fn handle_ll_login_accept(&mut self, signal: LoginAccept) {
-        unimplemented!()
+    fn accepts_packages(&self) -> bool {
+        self.state == ClientState::Loading || self.state == ClientState::Spawned
     }
 
-    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect) {
-        unimplemented!()
+    fn handle_ll_login_request(&mut self, signal: LoginRequest) -> VioResult { cs_only!() }
+
+    fn handle_ll_auth_challenge(&mut self, signal: AuthChallenge) -> VioResult {
+        if self.state != ClientState::LoginRequested {
+            return io_error("Received AUTH_CHALLENGE without LOGIN_REQUEST");
+        }
+        let response = match &self.config.credentials {
+            Some(credentials) => credentials.respond(&signal.nonce)?,
+            None => return io_error("The server requires authentication, but the client has no credentials"),
+        };
+        self.send(|writer| response.write(writer))?;
+        self.state = ClientState::Authenticating;
+        Result::Ok(())
     }
 
-    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect) { cs_only!() }
+    fn handle_ll_auth_response(&mut self, signal: AuthResponse) -> VioResult { cs_only!() }
+
+    fn handle_ll_login_accept(&mut self, signal: LoginAccept) -> VioResult {
+        if self.state != ClientState::LoginRequested && self.state != ClientState::Authenticating {
+            return io_error("Received LOGIN_ACCEPT without LOGIN_REQUEST");
+        }
+        let server = ProtocolVersion::new(self.config.version.major, signal.minor_protocol);
+        match ProtocolVersion::negotiate(self.config.version, server) {
+            Ok(version) => {
+                self.version = Some(version);
+                self.capabilities = self.config.capabilities.intersection(signal.capabilities);
+                self.state = match (signal.resumed, self.suspended.take()) {
+                    (true, Some(state)) => state,
+                    (true, None) => return io_error("The server resumed a session that the client did not suspend"),
+                    (false, _) => ClientState::Loading,
+                };
+                self.resumed = signal.resumed;
+                self.resume_token = signal.resume_token;
+                self.rejoin = false;
+                self.attempts = 0;
+            }
+            Err(rejection) => {
+                self.send(|writer| ClientDisconnect {}.write(writer))?;
+                self.state = ClientState::Disconnected;
+                self.adapter.on_version_rejected(&rejection);
+            }
+        }
+        Result::Ok(())
+    }
 
-    fn handle_ll_ping(&mut self, signal: Ping) {
-        unimplemented!()
+    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect) -> VioResult {
+        self.lose(signal.rejoin);
+        self.adapter.on_disconnect(DisconnectCode::Custom, signal.reason.as_str(), signal.rejoin);
+        Result::Ok(())
     }
 
-    fn handle_ll_pong(&mut self, signal: Pong) {
-        unimplemented!()
+    fn handle_ll_server_disconnect_reason(&mut self, signal: ServerDisconnectReason) -> VioResult {
+        self.lose(signal.rejoin);
+        self.adapter.on_disconnect(signal.code, signal.message.as_str(), signal.rejoin);
+        Result::Ok(())
     }
 
-    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal) {
-        unimplemented!()
+    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect) -> VioResult { cs_only!() }
+
+    fn handle_ll_ping(&mut self, signal: Ping) -> VioResult {
+        self.send(|writer| Pong {}.write(writer))
     }
 
-    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal) {
-        unimplemented!()
+    fn handle_ll_pong(&mut self, signal: Pong) -> VioResult {
+        Result::Ok(())
     }
 
-    fn handle_pk_spawn(&mut self, signal: SpawnSignal) {
-        unimplemented!()
+    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal) -> VioResult {
+        if self.state != ClientState::Loading && self.state != ClientState::Spawned {
+            return io_error("Received CUBE_BATCH before LOGIN_ACCEPT");
+        }
+        self.adapter.on_cube_batch(&signal);
+        Result::Ok(())
     }
-}
 
+    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal) -> VioResult {
+        if self.state != ClientState::Loading && self.state != ClientState::Spawned {
+            return io_error("Received CUBE_DICT before LOGIN_ACCEPT");
+        }
+        self.adapter.on_cube_dict(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_spawn(&mut self, signal: SpawnSignal) -> VioResult {
+        if self.state != ClientState::Loading {
+            return io_error("Received SPAWN outside the loading state");
+        }
+        self.state = ClientState::Spawned;
+        Result::Ok(())
+    }
+
+    fn handle_pk_cube_update(&mut self, signal: CubeUpdateSignal) -> VioResult {
+        if self.state != ClientState::Loading && self.state != ClientState::Spawned {
+            return io_error("Received CUBE_UPDATE before LOGIN_ACCEPT");
+        }
+        self.adapter.on_cube_update(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_cube_interact(&mut self, signal: CubeInteractSignal) -> VioResult { cs_only!() }
+
+    fn handle_pk_flex_motion(&mut self, signal: FlexMotionSignal) -> VioResult {
+        if self.state != ClientState::Loading && self.state != ClientState::Spawned {
+            return io_error("Received FLEX_MOTION before LOGIN_ACCEPT");
+        }
+        self.adapter.on_flex_motion(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_user_motion(&mut self, signal: UserMotionSignal) -> VioResult { cs_only!() }
+
+    fn handle_pk_user_flags(&mut self, signal: UserFlagsSignal) -> VioResult { cs_only!() }
+
+    fn handle_pk_flex_flags(&mut self, signal: FlexFlagsSignal) -> VioResult {
+        if self.state != ClientState::Loading && self.state != ClientState::Spawned {
+            return io_error("Received FLEX_FLAGS before LOGIN_ACCEPT");
+        }
+        self.adapter.on_flex_flags(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_world_switch(&mut self, signal: WorldSwitchSignal) -> VioResult {
+        if self.state != ClientState::Spawned {
+            return io_error("Received WORLD_SWITCH outside the spawned state");
+        }
+        self.state = ClientState::Loading;
+        self.adapter.on_world_switch(&signal);
+        Result::Ok(())
+    }
+}
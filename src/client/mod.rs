@@ -17,28 +17,223 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::time::Duration;
+
+use crate::auth::Credentials;
+use crate::io::writer::CubeWriter;
+use crate::protocol::{handle_frame, write_frame};
+use crate::protocol::capability::{CAPABILITIES_MINOR, Capability, CapabilitySet};
+use crate::protocol::ll::disconnect::DisconnectCode;
+use crate::protocol::ll::login_request::{LoginRequest, RESUME_MINOR};
+use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::cube_dict::CubeDictSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::version::{ProtocolVersion, VersionRejection};
+use crate::util::{io_error, VioResult};
+
+pub use self::backoff::Backoff;
+
+pub mod backoff;
+#[cfg(test)]
+mod backoff_test;
 mod handler;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ClientState {
     Initial,
     LoginRequested,
+    /// The client has answered AUTH_CHALLENGE
+    Authenticating,
     Loading,
     Spawned,
     Disconnected,
 }
 
+/// The identity sent to the server in LOGIN_REQUEST
+pub struct ClientConfig {
+    pub username: String,
+    pub user_id: [u8; 20],
+    pub language: String,
+    /// A JSON object describing the client, usually built with `SysInfo::to_json`
+    pub sys_info: String,
+    /// The protocol version requested in LOGIN_REQUEST, usually `ProtocolVersion::CURRENT`
+    pub version: ProtocolVersion,
+    /// The optional features supported by the client
+    pub capabilities: CapabilitySet,
+    /// Answers AUTH_CHALLENGE if the server requires authentication
+    pub credentials: Option<Box<dyn Credentials>>,
+    /// The delays between attempts to rejoin, see `Client::rejoin_delay`
+    pub backoff: Backoff,
+}
+
 pub struct Client<A> {
     state: ClientState,
+    config: ClientConfig,
+    version: Option<ProtocolVersion>,
+    capabilities: CapabilitySet,
+    /// The resume token of the last LOGIN_ACCEPT
+    resume_token: Vec<u8>,
+    resumed: bool,
+    /// The state of the session to resume on the next LOGIN_ACCEPT
+    suspended: Option<ClientState>,
+    /// Whether the client may rejoin after the last disconnection
+    rejoin: bool,
+    /// The number of reconnection attempts since the last LOGIN_ACCEPT
+    attempts: u32,
     adapter: A,
 }
 
 impl<A: ClientAdapter> Client<A> {
-    pub fn new(adapter: A) -> Client<A> {
+    pub fn new(adapter: A, config: ClientConfig) -> Client<A> {
         Client {
             state: ClientState::Initial,
+            config,
+            version: None,
+            capabilities: CapabilitySet::EMPTY,
+            resume_token: Vec::new(),
+            resumed: false,
+            suspended: None,
+            rejoin: false,
+            attempts: 0,
             adapter,
         }
     }
+
+    pub fn state(&self) -> ClientState { self.state }
+
+    /// The protocol version negotiated with the server, once LOGIN_ACCEPT is received
+    pub fn version(&self) -> Option<ProtocolVersion> { self.version }
+
+    /// The features supported by both the client and the server, once LOGIN_ACCEPT is received
+    pub fn capabilities(&self) -> CapabilitySet { self.capabilities }
+
+    pub fn supports(&self, capability: Capability) -> bool { self.capabilities.contains(capability) }
+
+    /// Whether the last LOGIN_ACCEPT resumed the previous session,
+    /// in which case the client keeps its cube dictionary, batches and position
+    pub fn is_resumed(&self) -> bool { self.resumed }
+
+    /// The time to wait before calling `reconnect`,
+    /// or `None` if the client is connected, may not rejoin or has given up
+    pub fn rejoin_delay(&self) -> Option<Duration> {
+        if self.state != ClientState::Disconnected || !self.rejoin {
+            return None;
+        }
+        self.config.backoff.delay(self.attempts)
+    }
+
+    pub fn adapter(&self) -> &A { &self.adapter }
+
+    pub fn adapter_mut(&mut self) -> &mut A { &mut self.adapter }
+
+    /// Sends LOGIN_REQUEST to the server.
+    /// Call this once the websocket connection is open.
+    pub fn connect(&mut self) -> VioResult {
+        let request = LoginRequest {
+            major_protocol: self.config.version.major,
+            minor_protocol: self.config.version.minor,
+            username: self.config.username.clone(),
+            user_id: self.config.user_id,
+            language: self.config.language.clone(),
+            sys_info: self.config.sys_info.clone(),
+            capabilities: if self.config.version.has_minor(CAPABILITIES_MINOR) {
+                self.config.capabilities
+            } else {
+                CapabilitySet::EMPTY
+            },
+            resume_token: if self.config.version.has_minor(RESUME_MINOR) {
+                self.resume_token.clone()
+            } else {
+                Vec::new()
+            },
+        };
+        self.send(|writer| request.write(writer))?;
+        self.state = ClientState::LoginRequested;
+        Result::Ok(())
+    }
+
+    /// Sends LOGIN_REQUEST again after a disconnection that allows rejoining,
+    /// resuming the previous session if the server still remembers it.
+    /// Call this once the new websocket connection is open, after waiting for `rejoin_delay`.
+    pub fn reconnect(&mut self) -> VioResult {
+        if self.state != ClientState::Disconnected {
+            return io_error("Cannot reconnect without disconnecting");
+        }
+        if !self.rejoin {
+            return io_error("The server does not allow rejoining");
+        }
+        self.attempts += 1;
+        self.version = None;
+        self.capabilities = CapabilitySet::EMPTY;
+        self.connect()
+    }
+
+    /// Call this when the connection is closed without a disconnect signal
+    pub fn connection_lost(&mut self) {
+        if self.state != ClientState::Disconnected {
+            self.lose(true);
+        }
+    }
+
+    /// Moves the client to the disconnected state, remembering the session to resume if `rejoin` is allowed
+    fn lose(&mut self, rejoin: bool) {
+        if !rejoin {
+            self.resume_token.clear();
+            self.suspended = None;
+        } else if self.state == ClientState::Loading || self.state == ClientState::Spawned {
+            self.suspended = Some(self.state);
+        }
+        self.rejoin = rejoin;
+        self.state = ClientState::Disconnected;
+    }
+
+    /// Handles a binary message received from the server
+    pub fn receive(&mut self, frame: &[u8]) -> VioResult {
+        self.adapter.on_receive(frame);
+        handle_frame(self, frame)
+    }
+
+    fn send<F>(&mut self, write: F) -> VioResult
+        where F: FnOnce(&mut CubeWriter<Vec<u8>>) -> VioResult {
+        let frame = write_frame(write)?;
+        self.adapter.send(frame)
+    }
 }
 
-pub trait ClientAdapter {}
+#[allow(unused_variables)]
+pub trait ClientAdapter {
+    /// Sends a binary message to the server
+    fn send(&mut self, frame: Vec<u8>) -> VioResult;
+
+    /// Called with every binary message received from the server, before it is handled
+    fn on_receive(&mut self, frame: &[u8]) {}
+
+    /// Called when the client disconnects because it cannot use the protocol version of the server
+    fn on_version_rejected(&mut self, rejection: &VersionRejection) {}
+
+    /// Called when the server disconnects the client.
+    /// Servers older than minor protocol 5 do not send a code, which is reported as `DisconnectCode::Custom`.
+    fn on_disconnect(&mut self, code: DisconnectCode, message: &str, rejoin: bool) {}
+
+    /// Called when the server sends the cube definitions of the world
+    fn on_cube_dict(&mut self, signal: &CubeDictSignal) {}
+
+    /// Called when a batch of cubes is loaded
+    fn on_cube_batch(&mut self, signal: &CubeBatchSignal) {}
+
+    /// Called when a cube is changed
+    fn on_cube_update(&mut self, signal: &CubeUpdateSignal) {}
+
+    /// Called when an entity moves
+    fn on_flex_motion(&mut self, signal: &FlexMotionSignal) {}
+
+    /// Called when an entity starts or stops crouching
+    fn on_flex_flags(&mut self, signal: &FlexFlagsSignal) {}
+
+    /// Called when the server moves the client to another world.
+    /// The client is back in the loading state and should drop its loaded batches and cube dictionary.
+    fn on_world_switch(&mut self, signal: &WorldSwitchSignal) {}
+}
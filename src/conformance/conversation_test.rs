@@ -0,0 +1,228 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use libflate::deflate::Decoder;
+
+use crate::conformance::{Conversation, lex_step_line, parse_step, preprocess_lines, Steps, StepToken};
+use crate::conformance::preprocess::MAX_INCLUDE_DEPTH;
+use crate::util::IoResult;
+
+fn lex(line: &str) -> Vec<StepToken> {
+    let mut tokens = Vec::new();
+    lex_step_line(line, &mut tokens).unwrap();
+    tokens
+}
+
+#[test]
+fn parse_literals() {
+    let bytes = parse_step(&lex("21, 00 02 #{h\\}} F{0.3} D{0.3} ; comment")).unwrap();
+    assert_eq!(bytes, vec![
+        0x21, 0x00, 0x02, 0x68, 0x7d,
+        0x3e, 0x99, 0x99, 0x9a,
+        0x3f, 0xd3, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33,
+    ]);
+}
+
+#[test]
+fn parse_zlib() {
+    let bytes = parse_step(&lex("e1 Z{ 80 01 02 } ff")).unwrap();
+    assert_eq!(bytes[0], 0xe1);
+    assert_eq!(bytes[bytes.len() - 1], 0xff);
+    let mut inflated = Vec::new();
+    Decoder::new(&bytes[1..bytes.len() - 1]).read_to_end(&mut inflated).unwrap();
+    assert_eq!(inflated, vec![0x80, 0x01, 0x02]);
+}
+
+#[test]
+fn parse_unbalanced() {
+    assert!(parse_step(&lex("00 }")).is_err());
+    assert!(parse_step(&lex("Z{ 00")).is_err());
+}
+
+#[test]
+fn lex_errors() {
+    let mut tokens = Vec::new();
+    assert!(lex_step_line("0", &mut tokens).is_err());
+    assert!(lex_step_line("X{}", &mut tokens).is_err());
+    assert!(lex_step_line("#{abc", &mut tokens).is_err());
+}
+
+#[test]
+fn steps() {
+    let lines = vec!["> 21", "00 01", "< 41", "> 62"].into_iter().map(str::to_owned);
+    let steps: Vec<_> = Steps::new(lines).map(|step| step.unwrap().encode().unwrap()).collect();
+    assert_eq!(steps.len(), 3);
+    assert!(!steps[0].from_server);
+    assert_eq!(steps[0].buffer, vec![0x21, 0x00, 0x01]);
+    assert!(steps[1].from_server);
+    assert_eq!(steps[2].buffer, vec![0x62]);
+}
+
+#[test]
+fn steps_without_direction() {
+    let mut steps = Steps::new(vec!["00 01".to_owned()].into_iter());
+    assert!(steps.next().unwrap().is_err());
+}
+
+#[test]
+fn encode_text() {
+    let conversation = Conversation::parse("example", "; comment\n> 21 #{A}\n\n< 41", Path::new(".")).unwrap();
+    let text = conversation.to_string();
+    assert_eq!(text, "; example\n> 21 41\n< 41\n");
+
+    let parsed = Conversation::parse("example", text.as_str(), Path::new(".")).unwrap();
+    assert_eq!(parsed.steps.len(), 2);
+    assert_eq!(parsed.steps[0].buffer, conversation.steps[0].buffer);
+}
+
+#[test]
+fn encode_tokens() {
+    let step = Steps::new(vec!["> 21 #{a\\}b} F{1.5} Z{ 00 }".to_owned()].into_iter()).next().unwrap().unwrap();
+    assert_eq!(step.to_string(), "> 21 #{a\\}b} F{1.5} Z{ 00 }");
+}
+
+#[test]
+fn parse_typed_literals() {
+    let bytes = parse_step(&lex("u8{255} u16{5} i16{-2} i32{-3} u32{0x12345678} u64{1} i8{-128} i64{-1}")).unwrap();
+    assert_eq!(bytes, vec![
+        0xff,
+        0x00, 0x05,
+        0xff, 0xfe,
+        0xff, 0xff, 0xff, 0xfd,
+        0x12, 0x34, 0x56, 0x78,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x80,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    ]);
+}
+
+#[test]
+fn parse_varints() {
+    let bytes = parse_step(&lex("varuint{300} varint{-1}")).unwrap();
+    assert_eq!(bytes, vec![0xac, 0x02, 0x01]);
+}
+
+#[test]
+fn parse_integer_out_of_range() {
+    let mut tokens = Vec::new();
+    assert!(lex_step_line("u8{256}", &mut tokens).is_err());
+    assert!(lex_step_line("u16{-1}", &mut tokens).is_err());
+    assert!(lex_step_line("i8{128}", &mut tokens).is_err());
+}
+
+#[test]
+fn parse_strings() {
+    let bytes = parse_step(&lex("str{Steve} str32{\\}}")).unwrap();
+    assert_eq!(bytes, vec![0x00, 0x05, 0x53, 0x74, 0x65, 0x76, 0x65, 0x00, 0x00, 0x00, 0x01, 0x7d]);
+}
+
+#[test]
+fn parse_bits() {
+    let bytes = parse_step(&lex("bits{1 0 1} bits{1} nop bits{10101010} 7f")).unwrap();
+    assert_eq!(bytes, vec![0xb0, 0xaa, 0x7f]);
+}
+
+#[test]
+fn parse_unpadded_bits() {
+    assert!(parse_step(&lex("bits{1} 00")).is_err());
+    assert!(parse_step(&lex("bits{1 1}")).is_err());
+}
+
+#[test]
+fn parse_signal_ids() {
+    let bytes = parse_step(&lex("LL_LOGIN_REQUEST LL_PACKAGE PK_LOAD_CUBE_BATCH")).unwrap();
+    assert_eq!(bytes, vec![0x21, 0xe1, 0x01, 0x02]);
+    let mut tokens = Vec::new();
+    assert!(lex_step_line("LL_NONEXISTENT", &mut tokens).is_err());
+}
+
+#[test]
+fn encode_typed_tokens() {
+    let line = "> LL_SERVER_DISCONNECT str{a\\}} bits{1 0} nop u16{5} i32{-3}";
+    let step = Steps::new(vec![line.to_owned()].into_iter()).next().unwrap().unwrap();
+    assert_eq!(step.to_string(), line);
+}
+
+fn preprocess(text: &str) -> IoResult<Vec<String>> {
+    preprocess_lines(text.lines().map(str::to_owned), Path::new("tests/hex/lib"))
+}
+
+#[test]
+fn preprocess_include_defaults() {
+    let lines = preprocess("+ login").unwrap();
+    assert_eq!(lines, vec![
+        "> LL_LOGIN_REQUEST",
+        "u32{1}, u32{6}",
+        "str{Steve}",
+        "12345678abcdefabcdef12345678abcdefabcdef",
+        "str{en_US} str{\\{\\}}",
+        "u64{0}",
+        "u16{0}",
+    ]);
+}
+
+#[test]
+fn preprocess_include_arguments() {
+    let lines = preprocess("+ login username=Alex minor=3").unwrap();
+    assert_eq!(lines[1], "u32{1}, u32{3}");
+    assert_eq!(lines[2], "str{Alex}");
+    assert_eq!(lines[4], "str{en_US} str{\\{\\}}");
+}
+
+#[test]
+fn preprocess_variables() {
+    let lines = preprocess("= name=Alex price=5\n> str{${name}} str{$$${price}}\n+ login username=${name}").unwrap();
+    assert_eq!(lines[0], "> str{Alex} str{$5}");
+    assert_eq!(lines[3], "str{Alex}");
+}
+
+#[test]
+fn preprocess_errors() {
+    assert!(preprocess("+ login password=hunter2").is_err());
+    assert!(preprocess("+ login username").is_err());
+    assert!(preprocess("> str{${name}}").is_err());
+    assert!(preprocess("> str{$name}").is_err());
+    assert!(preprocess("+").is_err());
+}
+
+#[test]
+fn preprocess_include_guard() {
+    let lib_dir = std::env::temp_dir().join(format!("cube-engine-include-guard-{}", std::process::id()));
+    fs::create_dir_all(&lib_dir).unwrap();
+    fs::write(lib_dir.join("self.txt"), "+ self\n").unwrap();
+    fs::write(lib_dir.join("ping.txt"), "> ping\n+ pong\n").unwrap();
+    fs::write(lib_dir.join("pong.txt"), "< pong\n+ ping\n").unwrap();
+    for depth in 0..=MAX_INCLUDE_DEPTH {
+        fs::write(lib_dir.join(format!("nested-{}.txt", depth)), format!("+ nested-{}\n", depth + 1)).unwrap();
+    }
+    fs::write(lib_dir.join(format!("nested-{}.txt", MAX_INCLUDE_DEPTH)), "> leaf\n").unwrap();
+
+    let preprocess = |text: &str| preprocess_lines(text.lines().map(str::to_owned), &lib_dir).map_err(|e| e.to_string());
+    assert!(preprocess("+ self").unwrap_err().ends_with("Include cycle self -> self"));
+    assert!(preprocess("+ ping").unwrap_err().ends_with("Include cycle ping -> pong -> ping"));
+    // `+ nested-1` expands MAX_INCLUDE_DEPTH levels and `+ nested-0` one more
+    assert_eq!(preprocess("+ nested-1").unwrap(), vec!["> leaf"]);
+    assert!(preprocess("+ nested-0").unwrap_err().contains("Includes nested deeper than"));
+
+    fs::remove_dir_all(&lib_dir).unwrap();
+}
@@ -0,0 +1,396 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::fmt::{Display, Error, Formatter};
+
+use crate::conformance::{IntType, parse_step, Step, StepToken};
+use crate::conformance::ids::{signal_id_name, SignalId};
+use crate::conformance::schema::{ArrayLength, Schema, SchemaItem, SchemaType, substitute_indices};
+use crate::io::reader::CubeReader;
+use crate::protocol::ll::package;
+use crate::util::{io_error, io_error_f, IoResult};
+
+/// One decoded signal or field
+pub struct Annotation {
+    /// The offset of the first byte, relative to the inflated package for PKs
+    pub offset: usize,
+    /// `0` for LLs, `1` for PKs and LL fields, `2` for PK fields
+    pub depth: usize,
+    pub name: String,
+    pub value: String,
+}
+
+/// The field-by-field decoding of a websocket binary message
+pub struct Disassembly {
+    pub annotations: Vec<Annotation>,
+    /// The message in the `tests/hex` syntax
+    pub tokens: Vec<StepToken>,
+}
+
+impl Disassembly {
+    pub fn into_step(self, from_server: bool) -> Step<StepToken> {
+        Step { from_server, buffer: self.tokens }
+    }
+}
+
+impl Display for Disassembly {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        for annotation in &self.annotations {
+            write!(f, "{:04x} {:indent$}{}", annotation.offset, "", annotation.name, indent = annotation.depth * 2)?;
+            if !annotation.value.is_empty() {
+                write!(f, ": {}", annotation.value)?;
+            }
+            writeln!(f)?;
+        }
+        Result::Ok(())
+    }
+}
+
+/// A decoded field value
+enum Value {
+    Int(i128),
+    Str(String),
+    Other(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{:?}", value),
+            Value::Other(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// The fields decoded so far in a signal or struct, used to resolve loop counts, array lengths and conditions
+#[derive(Default)]
+struct Scope {
+    indices: Vec<(String, usize)>,
+    values: HashMap<String, Value>,
+}
+
+impl Scope {
+    fn int(&self, name: &str) -> IoResult<i128> {
+        match self.values.get(name) {
+            Some(Value::Int(value)) => Result::Ok(*value),
+            _ => io_error_f(format!("{} is not a previous integer field", name)),
+        }
+    }
+}
+
+/// Decodes a websocket binary message field by field, following the signal formats in `protocol/spec.txt`.
+///
+/// Data that cannot be decoded is listed as raw bytes instead of failing,
+/// so this can be used to diagnose malformed messages.
+/// Packages are re-emitted as `Z{...}` sections, so the emitted tokens encode the same signals,
+/// but the deflated bytes may differ from `buffer` if it was deflated by another encoder.
+/// Packages that inflate past `MAX_PACKAGE_INFLATED` are listed as raw deflated bytes.
+pub fn disassemble(buffer: &[u8]) -> Disassembly {
+    let mut disassembly = Disassembly { annotations: Vec::new(), tokens: Vec::new() };
+    let mut decoder = Decoder::new(buffer, 0, &mut disassembly);
+    decoder.signal(Decoder::ll);
+    if decoder.reader.remaining() > 0 {
+        let offset = decoder.offset();
+        decoder.raw(offset, "trailing bytes");
+    }
+    disassembly
+}
+
+struct Decoder<'a, 'd> {
+    buffer: &'a [u8],
+    reader: CubeReader<&'a [u8]>,
+    depth: usize,
+    out: &'d mut Disassembly,
+}
+
+impl<'a, 'd> Decoder<'a, 'd> {
+    fn new(buffer: &'a [u8], depth: usize, out: &'d mut Disassembly) -> Decoder<'a, 'd> {
+        Decoder { buffer, reader: CubeReader::new(buffer), depth, out }
+    }
+
+    fn offset(&self) -> usize {
+        self.buffer.len() - self.reader.remaining()
+    }
+
+    fn annotate(&mut self, offset: usize, depth: usize, name: &str, value: String) {
+        self.out.annotations.push(Annotation { offset, depth: self.depth + depth, name: name.to_owned(), value });
+    }
+
+    /// Lists `buffer[offset..]` as raw bytes
+    fn raw(&mut self, offset: usize, reason: &str) {
+        self.raw_range(offset, self.buffer.len(), reason);
+    }
+
+    /// Lists `buffer[offset..end]` as raw bytes
+    fn raw_range(&mut self, offset: usize, end: usize, reason: &str) {
+        let bytes = &self.buffer[offset..end];
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.annotate(offset, 0, reason, hex.join(" "));
+        self.out.tokens.extend(bytes.iter().map(|&byte| StepToken::Byte(byte)));
+    }
+
+    /// Runs `decode`, replacing its output with raw bytes if it fails
+    fn signal<T, F: FnOnce(&mut Self) -> IoResult<T>>(&mut self, decode: F) -> Option<T> {
+        let offset = self.offset();
+        let annotations = self.out.annotations.len();
+        let tokens = self.out.tokens.len();
+        match decode(self) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.out.annotations.truncate(annotations);
+                self.out.tokens.truncate(tokens);
+                self.raw(offset, format!("undecoded ({})", err).as_str());
+                self.reader = CubeReader::new(&self.buffer[self.buffer.len()..]);
+                None
+            }
+        }
+    }
+
+    fn signal_name(&mut self, offset: usize, id: SignalId) -> IoResult<()> {
+        let name = match signal_id_name(id) {
+            Some(name) => name,
+            None => return io_error("Unknown signal ID"),
+        };
+        self.annotate(offset, 0, name, String::new());
+        self.out.tokens.push(StepToken::SignalId(name.to_owned(), id));
+        Result::Ok(())
+    }
+
+    fn ll(&mut self) -> IoResult<()> {
+        let offset = self.offset();
+        let id = self.reader.read_uint8()?;
+        self.signal_name(offset, SignalId::LowLevel(id))?;
+        if id == package::LL_PACKAGE {
+            return self.package();
+        }
+        self.schema_signal(SignalId::LowLevel(id))
+    }
+
+    fn schema_signal(&mut self, id: SignalId) -> IoResult<()> {
+        let signal = match Schema::spec().find_signal(id) {
+            Some(signal) => signal,
+            None => return io_error("Signal is not described in protocol/spec.txt"),
+        };
+        self.items(&signal.items, &mut Scope::default(), true, &mut Vec::new())
+    }
+
+    fn package(&mut self) -> IoResult<()> {
+        let offset = self.offset();
+        let size = self.reader.read_uint32()? as usize;
+        let deflated = self.reader.read_slice(size)?;
+        let inflated = match package::inflate(deflated) {
+            Ok(inflated) => inflated,
+            Err(err) => {
+                // listed without inflating, so that a package bomb cannot exhaust the memory
+                self.annotate(offset, 1, "length", size.to_string());
+                self.out.tokens.push(StepToken::Integer(IntType::U32, size as i128));
+                let end = self.offset();
+                self.raw_range(end - size, end, format!("deflated ({})", err).as_str());
+                return Result::Ok(());
+            }
+        };
+        self.annotate(offset, 1, "length", format!("{} (inflated to {} bytes)", size, inflated.len()));
+
+        let mut inner = Disassembly { annotations: Vec::new(), tokens: vec![StepToken::StartZlib] };
+        {
+            let mut decoder = Decoder::new(inflated.as_slice(), self.depth + 1, &mut inner);
+            while decoder.reader.remaining() > 0 {
+                let more = decoder.signal(|decoder| {
+                    let offset = decoder.offset();
+                    let more = decoder.reader.read_bit()?;
+                    decoder.reader.read_nop()?;
+                    decoder.out.tokens.push(StepToken::Bits(vec![more]));
+                    decoder.out.tokens.push(StepToken::Nop);
+                    if !more {
+                        decoder.annotate(offset, 0, "end", String::new());
+                        return Result::Ok(false);
+                    }
+                    let offset = decoder.offset();
+                    let id = decoder.reader.read_uint16()?;
+                    decoder.signal_name(offset, SignalId::Packed(id))?;
+                    decoder.schema_signal(SignalId::Packed(id))?;
+                    Result::Ok(true)
+                });
+                if more != Some(true) {
+                    break;
+                }
+            }
+            if decoder.reader.remaining() > 0 {
+                let offset = decoder.offset();
+                decoder.raw(offset, "trailing bytes");
+            }
+        }
+        inner.tokens.push(StepToken::Close);
+
+        let size = parse_step(&inner.tokens)?.len();
+        self.out.tokens.push(StepToken::Integer(IntType::U32, size as i128));
+        self.out.tokens.append(&mut inner.tokens);
+        self.out.annotations.append(&mut inner.annotations);
+        Result::Ok(())
+    }
+
+    /// Decodes schema items, collecting `name: value` of each field into `parts`.
+    /// Fields are also annotated if `annotate` is set.
+    fn items(&mut self, items: &[SchemaItem], scope: &mut Scope, annotate: bool, parts: &mut Vec<String>) -> IoResult<()> {
+        for item in items {
+            match item {
+                SchemaItem::Field { name, field_type } => {
+                    let name = substitute_indices(name, &scope.indices);
+                    let offset = self.offset();
+                    let value = self.value(field_type, scope)?;
+                    if annotate {
+                        self.annotate(offset, 1, &name, value.to_string());
+                    }
+                    parts.push(format!("{}: {}", name, value));
+                    scope.values.insert(name, value);
+                }
+                SchemaItem::Nop => {
+                    self.reader.read_nop()?;
+                    self.out.tokens.push(StepToken::Nop);
+                }
+                SchemaItem::Todo => return io_error("The format is not specified yet"),
+                SchemaItem::Repeat { index, count, items } => {
+                    let count = scope.int(count)?;
+                    for i in 1..=count {
+                        scope.indices.push((index.clone(), i as usize));
+                        self.items(items, scope, annotate, parts)?;
+                        scope.indices.pop();
+                    }
+                }
+                SchemaItem::UnlessPrefix { field, prefix, items } => {
+                    let field = substitute_indices(field, &scope.indices);
+                    let matches = match scope.values.get(&field) {
+                        Some(Value::Str(value)) => value.starts_with(prefix.as_str()),
+                        _ => return io_error_f(format!("{} is not a previous string field", field)),
+                    };
+                    if !matches {
+                        self.items(items, scope, annotate, parts)?;
+                    }
+                }
+                SchemaItem::IfAtLeast { field, min, items } => {
+                    if scope.int(&substitute_indices(field, &scope.indices))? >= *min as i128 {
+                        self.items(items, scope, annotate, parts)?;
+                    }
+                }
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn value(&mut self, field_type: &SchemaType, scope: &Scope) -> IoResult<Value> {
+        Result::Ok(match field_type {
+            SchemaType::Int { signed, bits } => {
+                let (value, int_type) = match (signed, bits) {
+                    (true, 8) => (self.reader.read_int8()? as i128, IntType::I8),
+                    (true, 16) => (self.reader.read_int16()? as i128, IntType::I16),
+                    (true, 32) => (self.reader.read_int32()? as i128, IntType::I32),
+                    (true, 64) => (self.reader.read_int64()? as i128, IntType::I64),
+                    (false, 8) => (self.reader.read_uint8()? as i128, IntType::U8),
+                    (false, 16) => (self.reader.read_uint16()? as i128, IntType::U16),
+                    (false, 32) => (self.reader.read_uint32()? as i128, IntType::U32),
+                    (false, 64) => (self.reader.read_uint64()? as i128, IntType::U64),
+                    _ => return io_error("Unsupported integer width"),
+                };
+                self.out.tokens.push(StepToken::Integer(int_type, value));
+                Value::Int(value)
+            }
+            SchemaType::Float32 => {
+                let value = self.reader.read_float32()?;
+                Value::Other(self.float(value))
+            }
+            SchemaType::Float64 => {
+                let value = self.reader.read_float64()?;
+                if value.is_finite() {
+                    self.out.tokens.push(StepToken::Double(value));
+                } else {
+                    self.out.tokens.extend(value.to_bits().to_be_bytes().iter().map(|&byte| StepToken::Byte(byte)));
+                }
+                Value::Other(format!("{:?}", value))
+            }
+            SchemaType::Bool => {
+                let value = self.reader.read_bit()?;
+                self.out.tokens.push(StepToken::Bits(vec![value]));
+                Value::Other(value.to_string())
+            }
+            SchemaType::Nibble => self.bits(4)?,
+            SchemaType::Bits(width) => self.bits(*width)?,
+            SchemaType::Byte => {
+                let value = self.reader.read_uint8()?;
+                self.out.tokens.push(StepToken::Byte(value));
+                Value::Int(value as i128)
+            }
+            SchemaType::String => {
+                let value = self.reader.read_str()?;
+                self.out.tokens.push(StepToken::Str(value.to_owned()));
+                Value::Str(value.to_owned())
+            }
+            SchemaType::String32 => {
+                let value = self.reader.read_str32()?;
+                self.out.tokens.push(StepToken::Str32(value.to_owned()));
+                Value::Str(value.to_owned())
+            }
+            SchemaType::Array(element, length) => {
+                let length = match length {
+                    ArrayLength::Fixed(length) => *length,
+                    ArrayLength::Field(name) => scope.int(name)? as usize,
+                };
+                if **element == SchemaType::Byte {
+                    let bytes = self.reader.read_slice(length)?;
+                    self.out.tokens.extend(bytes.iter().map(|&byte| StepToken::Byte(byte)));
+                    return Result::Ok(Value::Other(bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")));
+                }
+                let mut values = Vec::with_capacity(length);
+                for _ in 0..length {
+                    values.push(self.value(element, scope)?.to_string());
+                }
+                Value::Other(if values.len() > 8 {
+                    format!("[{}, ... ({} entries)]", values[0..8].join(", "), length)
+                } else {
+                    format!("[{}]", values.join(", "))
+                })
+            }
+            SchemaType::Struct(name) => {
+                let schema = match Schema::spec().find_struct(name) {
+                    Some(schema) => schema,
+                    None => return io_error_f(format!("Unknown type {}", name)),
+                };
+                let mut parts = Vec::new();
+                self.items(&schema.items, &mut Scope::default(), false, &mut parts)?;
+                Value::Other(format!("{{{}}}", parts.join(", ")))
+            }
+        })
+    }
+
+    fn float(&mut self, value: f32) -> String {
+        if value.is_finite() {
+            self.out.tokens.push(StepToken::Float(value));
+        } else {
+            self.out.tokens.extend(value.to_bits().to_be_bytes().iter().map(|&byte| StepToken::Byte(byte)));
+        }
+        format!("{:?}", value)
+    }
+
+    fn bits(&mut self, width: u8) -> IoResult<Value> {
+        let value = self.reader.read_bits(width)?;
+        self.out.tokens.push(StepToken::Bits((0..width).map(|i| value & (1 << (width - 1 - i)) != 0).collect()));
+        Result::Ok(Value::Int(value as i128))
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use crate::conformance::{disassemble, lex_step_line, parse_step, Step, StepToken};
+
+fn encode(line: &str) -> Vec<u8> {
+    let mut tokens = Vec::new();
+    lex_step_line(line, &mut tokens).unwrap();
+    parse_step(&tokens).unwrap()
+}
+
+/// Builds an LL_PACKAGE around the inflated content given in the `tests/hex` syntax
+fn package(content: &str) -> Vec<u8> {
+    let deflated = encode(format!("Z{{ {} }}", content).as_str());
+    let mut buffer = vec![0xe1];
+    buffer.extend_from_slice(&(deflated.len() as u32).to_be_bytes());
+    buffer.extend(deflated);
+    buffer
+}
+
+fn names(buffer: &[u8]) -> Vec<String> {
+    disassemble(buffer).annotations.into_iter().map(|annotation| annotation.name).collect()
+}
+
+fn reassemble(buffer: &[u8]) -> Vec<u8> {
+    parse_step(&disassemble(buffer).tokens).unwrap()
+}
+
+#[test]
+fn disassemble_login_request() {
+    let buffer = encode("LL_LOGIN_REQUEST u32{1} u32{1} str{Steve} 12 34 56 78 ab cd ef ab cd ef 12 34 56 78 ab cd ef ab cd ef str{en_US} str{\\{\\}}");
+    let disassembly = disassemble(&buffer);
+    let fields: Vec<(usize, &str, &str)> = disassembly.annotations.iter()
+        .map(|annotation| (annotation.offset, annotation.name.as_str(), annotation.value.as_str()))
+        .collect();
+    assert_eq!(fields, vec![
+        (0, "LL_LOGIN_REQUEST", ""),
+        (1, "majorProtocol", "1"),
+        (5, "minorProtocol", "1"),
+        (9, "username", "\"Steve\""),
+        (16, "userId", "12 34 56 78 ab cd ef ab cd ef 12 34 56 78 ab cd ef ab cd ef"),
+        (36, "language", "\"en_US\""),
+        (43, "sysInfo", "\"{}\""),
+    ]);
+    assert_eq!(parse_step(&disassembly.tokens).unwrap(), buffer);
+}
+
+#[test]
+fn disassemble_bits() {
+    let buffer = encode("LL_SERVER_DISCONNECT str{Kicked} bits{1} nop");
+    assert_eq!(names(&buffer), vec!["LL_SERVER_DISCONNECT", "reason", "rejoin"]);
+    assert_eq!(reassemble(&buffer), buffer);
+}
+
+#[test]
+fn disassemble_package() {
+    let buffer = package("bits{1} nop PK_SPAWN_SPAWN i32{1} i32{-2} i32{3} F{0.5} F{1.5} F{2.5} F{0.25} F{-0.25} \
+        bits{1} nop PK_LOAD_CUBE_DICT u32{2} u32{1} str{CubePump.Air} u32{2} str{CubePump.Dirt} \
+        bits{0} nop");
+    assert_eq!(names(&buffer), vec![
+        "LL_PACKAGE", "length",
+        "PK_SPAWN_SPAWN", "pos",
+        "PK_LOAD_CUBE_DICT", "size", "cubeDefId[1]", "cubeDefName[1]", "cubeDefId[2]", "cubeDefName[2]",
+        "end",
+    ]);
+    let disassembly = disassemble(&buffer);
+    assert_eq!(disassembly.annotations[3].value, "{batch: {x: 1, y: -2, z: 3}, local: {x: 0.5, y: 1.5, z: 2.5}, yaw: 0.25, pitch: -0.25}");
+    assert_eq!(disassembly.annotations[3].depth, 2);
+    assert_eq!(disassembly.annotations[3].offset, 3);
+    assert_eq!(reassemble(&buffer), buffer);
+}
+
+#[test]
+fn disassemble_non_finite_floats() {
+    let buffer = package("bits{1} nop PK_SPAWN_SPAWN i32{0} i32{0} i32{0} 7f c0 00 00 7f 80 00 00 F{0} F{0} F{0} bits{0} nop");
+    assert_eq!(reassemble(&buffer), buffer);
+}
+
+#[test]
+fn disassemble_malformed() {
+    assert_eq!(names(&[0x08]), vec!["undecoded (Unknown signal ID)"]);
+    assert_eq!(names(&encode("LL_LOGIN_ACCEPT 00 00")), vec!["undecoded (failed to fill whole buffer)"]);
+    assert_eq!(names(&encode("LL_PONG ff")), vec!["LL_PONG", "trailing bytes"]);
+
+    // Cube models are not specified yet
+    let buffer = package("bits{1} nop PK_LOAD_CUBE_DICT u32{1} u32{1} str{Custom.Cube} bits{0} nop");
+    assert_eq!(names(&buffer), vec!["LL_PACKAGE", "length", "undecoded (The format is not specified yet)"]);
+
+    let buffer = package("bits{1} nop PK_SPAWN_SPAWN 00 bits{0} nop");
+    assert_eq!(names(&buffer), vec!["LL_PACKAGE", "length", "undecoded (failed to fill whole buffer)"]);
+    assert_eq!(reassemble(&buffer), buffer);
+
+    let buffer = encode("LL_PONG ff");
+    assert_eq!(reassemble(&buffer), buffer);
+}
+
+#[test]
+fn disassemble_to_step() {
+    let buffer = encode("LL_LOGIN_ACCEPT u32{1}");
+    let step = disassemble(&buffer).into_step(true);
+    assert_eq!(step.to_string(), "< LL_LOGIN_ACCEPT u32{1}");
+
+    let mut tokens = Vec::new();
+    lex_step_line(&step.to_string()[2..], &mut tokens).unwrap();
+    let step = Step::<StepToken> { from_server: true, buffer: tokens };
+    assert_eq!(step.encode().unwrap().buffer, buffer);
+}
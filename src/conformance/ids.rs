@@ -0,0 +1,43 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::protocol::ids::{LowLevelId, PackedId};
+
+/// A signal ID referenced by name in a conversation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalId {
+    LowLevel(u8),
+    Packed(u16),
+}
+
+/// Resolves a name in `protocol/ll-id.txt` or `protocol/pk-id.txt`
+pub fn resolve_signal_id(name: &str) -> Option<SignalId> {
+    if let Some(id) = LowLevelId::from_name(name) {
+        return Some(SignalId::LowLevel(id.id()));
+    }
+    PackedId::from_name(name).map(|id| SignalId::Packed(id.id()))
+}
+
+/// Returns the name of a signal ID in `protocol/ll-id.txt` or `protocol/pk-id.txt`
+pub fn signal_id_name(id: SignalId) -> Option<&'static str> {
+    match id {
+        SignalId::LowLevel(id) => LowLevelId::from_id(id).map(LowLevelId::name),
+        SignalId::Packed(id) => PackedId::from_id(id).map(PackedId::name),
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use crate::conformance::{IntType, StepToken};
+use crate::conformance::ids::resolve_signal_id;
+use crate::util::{io_error, io_error_f, IoResult, VioResult};
+
+fn is_hex_digit(c: char) -> bool {
+    c.is_ascii_digit() || ('a'..='f').contains(&c)
+}
+
+/// Appends the tokens in a line of buffer data to `buffer`
+pub fn lex_step_line(line: &str, buffer: &mut Vec<StepToken>) -> VioResult {
+    let mut chars = line.chars().peekable();
+    while let Some(next) = chars.next() {
+        match next {
+            ' ' | '\t' | ',' => continue,
+            ';' => break,
+            '}' => buffer.push(StepToken::Close),
+            '#' => {
+                expect_open_brace(&mut chars, "#")?;
+                buffer.push(StepToken::Utf8String(scan_utf8(&mut chars)?));
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() && c != '_' { break; }
+                    word.push(c);
+                    chars.next();
+                }
+                if chars.peek() == Some(&'{') {
+                    chars.next();
+                    lex_literal(word.as_str(), &mut chars, buffer)?;
+                } else {
+                    lex_word(word.as_str(), buffer)?;
+                }
+            }
+            _ => {
+                let mut message = "Unexpected token ".to_owned();
+                message.push(next);
+                io_error_f(message)?
+            }
+        };
+    }
+    Result::Ok(())
+}
+
+fn expect_open_brace(chars: &mut Peekable<Chars>, prefix: &str) -> VioResult {
+    match chars.next() {
+        Some('{') => Result::Ok(()),
+        Some(c) => io_error_f(format!("Unexpected token '{}{}'", prefix, c)),
+        None => io_error_f(format!("Unexpected token '{}'", prefix)),
+    }
+}
+
+fn lex_word(word: &str, buffer: &mut Vec<StepToken>) -> VioResult {
+    if word == "nop" {
+        buffer.push(StepToken::Nop);
+        return Result::Ok(());
+    }
+    if let Some(id) = resolve_signal_id(word) {
+        buffer.push(StepToken::SignalId(word.to_owned(), id));
+        return Result::Ok(());
+    }
+    if !word.chars().all(is_hex_digit) {
+        return io_error_f(format!("Unexpected token {}", word));
+    }
+    if word.len() & 1 != 0 {
+        return io_error("Unexpected singleton nibble");
+    }
+    let digits: Vec<u32> = word.chars().map(|c| c.to_digit(16).unwrap()).collect();
+    for pair in digits.chunks(2) {
+        buffer.push(StepToken::Byte((pair[0] << 4 | pair[1]) as u8));
+    }
+    Result::Ok(())
+}
+
+fn lex_literal(keyword: &str, chars: &mut Peekable<Chars>, buffer: &mut Vec<StepToken>) -> VioResult {
+    let token = match keyword {
+        "Z" => StepToken::StartZlib,
+        "F" => StepToken::Float(scan_float::<f32>(chars)?),
+        "D" => StepToken::Double(scan_float::<f64>(chars)?),
+        "str" => StepToken::Str(scan_utf8(chars)?),
+        "str32" => StepToken::Str32(scan_utf8(chars)?),
+        "bits" => StepToken::Bits(scan_bits(chars)?),
+        _ => match IntType::from_name(keyword) {
+            Some(int_type) => StepToken::Integer(int_type, scan_integer(int_type, chars)?),
+            None => io_error_f(format!("Unexpected token '{}{{'", keyword))?,
+        },
+    };
+    buffer.push(token);
+    Result::Ok(())
+}
+
+fn scan_utf8(chars: &mut Peekable<Chars>) -> IoResult<String> {
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some(c2) => string.push(c2),
+                None => io_error("Unexpected end of line while parsing UTF-8 literal")?,
+            },
+            Some('}') => return Result::Ok(string),
+            Some(c) => string.push(c),
+            None => io_error("Unexpected end of line while parsing UTF-8 literal")?,
+        }
+    }
+}
+
+/// Reads the content of a literal until the closing brace, ignoring whitespace
+fn scan_content(chars: &mut Peekable<Chars>, kind: &str) -> IoResult<String> {
+    let mut read = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => return Result::Ok(read),
+            Some(c) if c == ' ' || c == '\t' => continue,
+            Some(c) => read.push(c),
+            None => io_error_f(format!("Unexpected end of line while parsing {} literal", kind))?,
+        }
+    }
+}
+
+fn scan_float<F: FromStr>(chars: &mut Peekable<Chars>) -> IoResult<F> {
+    let read = scan_content(chars, "float")?;
+    if !read.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == 'e') {
+        io_error("Unexpected token while parsing float literal")?;
+    }
+    match read.parse::<F>() {
+        Ok(n) => Result::Ok(n),
+        Err(_) => io_error("Error parsing float"),
+    }
+}
+
+fn scan_integer(int_type: IntType, chars: &mut Peekable<Chars>) -> IoResult<i128> {
+    let read = scan_content(chars, "integer")?;
+    let (negative, digits) = match read.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, read.as_str()),
+    };
+    let magnitude = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse::<i128>(),
+    };
+    let value = match magnitude {
+        Ok(n) if negative => -n,
+        Ok(n) => n,
+        Err(_) => io_error_f(format!("Error parsing integer \"{}\"", read))?,
+    };
+    let (min, max) = int_type.range();
+    if value < min || value > max {
+        io_error_f(format!("{} is out of range for {}", value, int_type.name()))?;
+    }
+    Result::Ok(value)
+}
+
+fn scan_bits(chars: &mut Peekable<Chars>) -> IoResult<Vec<bool>> {
+    let read = scan_content(chars, "bits")?;
+    let mut bits = Vec::new();
+    for c in read.chars() {
+        match c {
+            '0' => bits.push(false),
+            '1' => bits.push(true),
+            _ => io_error("Unexpected token while parsing bits literal")?,
+        }
+    }
+    Result::Ok(bits)
+}
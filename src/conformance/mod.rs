@@ -0,0 +1,224 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Reads the hex conversation fixtures described in `tests/hex/README.md`.
+//!
+//! A conversation is a sequence of steps, each being one websocket binary message
+//! sent by either the client (`>`) or the server (`<`).
+
+use std::fmt::{Display, Error, Formatter};
+use std::path::Path;
+
+use crate::util::IoResult;
+
+pub use self::disasm::{Annotation, disassemble, Disassembly};
+pub use self::ids::{resolve_signal_id, signal_id_name, SignalId};
+pub use self::lexer::lex_step_line;
+pub use self::parser::parse_step;
+pub use self::preprocess::{preprocess_file, preprocess_lines};
+pub use self::read::{read_conversation_file, read_conversations, Steps};
+pub use self::record::{RecordedFrame, Recorder};
+pub use self::schema::{ArrayLength, Direction, Schema, SchemaItem, SchemaSignal, SchemaStruct, SchemaType};
+
+mod disasm;
+mod ids;
+mod lexer;
+mod parser;
+mod preprocess;
+mod read;
+mod record;
+mod schema;
+#[cfg(test)]
+mod conversation_test;
+#[cfg(test)]
+mod disasm_test;
+#[cfg(test)]
+mod record_test;
+#[cfg(test)]
+mod schema_test;
+
+pub enum StepToken {
+    Byte(u8),
+    StartZlib,
+    Utf8String(String),
+    Float(f32),
+    Double(f64),
+    Close,
+    /// A big-endian or variable-length integer, e.g. `u16{5}`
+    Integer(IntType, i128),
+    /// A string with u16 length prefix, e.g. `str{Steve}`
+    Str(String),
+    /// A string with u32 length prefix, e.g. `str32{Steve}`
+    Str32(String),
+    /// Bit fields, e.g. `bits{1 0 1}`
+    Bits(Vec<bool>),
+    /// Pads the bit fields to a complete byte
+    Nop,
+    /// A signal ID referenced by its name in `ll-id.txt` or `pk-id.txt`
+    SignalId(String, SignalId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    VarUint,
+    VarInt,
+}
+
+const INT_TYPES: [IntType; 10] = [
+    IntType::U8, IntType::U16, IntType::U32, IntType::U64,
+    IntType::I8, IntType::I16, IntType::I32, IntType::I64,
+    IntType::VarUint, IntType::VarInt,
+];
+
+impl IntType {
+    pub fn name(self) -> &'static str {
+        match self {
+            IntType::U8 => "u8",
+            IntType::U16 => "u16",
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+            IntType::I8 => "i8",
+            IntType::I16 => "i16",
+            IntType::I32 => "i32",
+            IntType::I64 => "i64",
+            IntType::VarUint => "varuint",
+            IntType::VarInt => "varint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IntType> {
+        INT_TYPES.iter().cloned().find(|int_type| int_type.name() == name)
+    }
+
+    /// Returns the inclusive range of values of this type
+    pub fn range(self) -> (i128, i128) {
+        match self {
+            IntType::U8 => (0, u8::MAX as i128),
+            IntType::U16 => (0, u16::MAX as i128),
+            IntType::U32 => (0, u32::MAX as i128),
+            IntType::U64 | IntType::VarUint => (0, u64::MAX as i128),
+            IntType::I8 => (i8::MIN as i128, i8::MAX as i128),
+            IntType::I16 => (i16::MIN as i128, i16::MAX as i128),
+            IntType::I32 => (i32::MIN as i128, i32::MAX as i128),
+            IntType::I64 | IntType::VarInt => (i64::MIN as i128, i64::MAX as i128),
+        }
+    }
+}
+
+pub struct Step<S> {
+    pub from_server: bool,
+    pub buffer: Vec<S>,
+}
+
+impl Step<StepToken> {
+    /// Encodes the tokens into the message bytes
+    pub fn encode(&self) -> IoResult<Step<u8>> {
+        Result::Ok(Step {
+            from_server: self.from_server,
+            buffer: parse_step(&self.buffer)?,
+        })
+    }
+}
+
+fn direction(from_server: bool) -> char {
+    if from_server { '<' } else { '>' }
+}
+
+impl Display for Step<u8> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", direction(self.from_server))?;
+        for byte in &self.buffer {
+            write!(f, " {:02x}", byte)?;
+        }
+        Result::Ok(())
+    }
+}
+
+impl Display for Step<StepToken> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", direction(self.from_server))?;
+        for token in &self.buffer {
+            match token {
+                StepToken::Byte(byte) => write!(f, " {:02x}", byte)?,
+                StepToken::StartZlib => write!(f, " Z{{")?,
+                StepToken::Utf8String(string) => write_escaped(f, "#", string)?,
+                StepToken::Float(value) => write!(f, " F{{{:?}}}", value)?,
+                StepToken::Double(value) => write!(f, " D{{{:?}}}", value)?,
+                StepToken::Close => write!(f, " }}")?,
+                StepToken::Integer(int_type, value) => write!(f, " {}{{{}}}", int_type.name(), value)?,
+                StepToken::Str(string) => write_escaped(f, "str", string)?,
+                StepToken::Str32(string) => write_escaped(f, "str32", string)?,
+                StepToken::Bits(bits) => {
+                    let bits: Vec<&str> = bits.iter().map(|&bit| if bit { "1" } else { "0" }).collect();
+                    write!(f, " bits{{{}}}", bits.join(" "))?;
+                }
+                StepToken::Nop => write!(f, " nop")?,
+                StepToken::SignalId(name, _) => write!(f, " {}", name)?,
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+fn write_escaped(f: &mut Formatter, prefix: &str, string: &str) -> Result<(), Error> {
+    write!(f, " {}{{", prefix)?;
+    for c in string.chars() {
+        if c == '\\' || c == '{' || c == '}' {
+            write!(f, "\\")?;
+        }
+        write!(f, "{}", c)?;
+    }
+    write!(f, "}}")
+}
+
+pub struct Conversation {
+    pub name: String,
+    pub steps: Vec<Step<u8>>,
+}
+
+impl Conversation {
+    /// Parses a conversation from its text, resolving includes from `lib_dir`
+    pub fn parse(name: &str, text: &str, lib_dir: &Path) -> IoResult<Conversation> {
+        let lines = preprocess_lines(text.lines().map(str::to_owned), lib_dir)?;
+        let mut steps = Vec::new();
+        for step in Steps::new(lines.into_iter()) {
+            steps.push(step?.encode()?);
+        }
+        Result::Ok(Conversation { name: name.to_owned(), steps })
+    }
+}
+
+/// Encodes the conversation back to text, with every step as plain hex pairs
+impl Display for Conversation {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(f, "; {}", self.name)?;
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Result::Ok(())
+    }
+}
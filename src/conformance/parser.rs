@@ -0,0 +1,100 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Write;
+
+use libflate::deflate::Encoder;
+
+use crate::conformance::{IntType, StepToken};
+use crate::conformance::ids::SignalId;
+use crate::io::writer::CubeWriter;
+use crate::util::{io_error, IoResult, VioResult};
+
+/// Encodes the tokens of a step into bytes
+pub fn parse_step(tokens: &[StepToken]) -> IoResult<Vec<u8>> {
+    let mut writer = CubeWriter::new(Vec::<u8>::new());
+    let mut i = 0;
+    if parse_until_end(tokens, &mut i, &mut writer)? { io_error("Unexpected close brace")? }
+    ensure_aligned(&writer)?;
+    Result::Ok(writer.target)
+}
+
+fn ensure_aligned<W: Write>(writer: &CubeWriter<W>) -> VioResult {
+    if !writer.is_complete_byte() {
+        io_error("Bit fields must be padded to a complete byte with nop")?;
+    }
+    Result::Ok(())
+}
+
+fn parse_until_end<W: Write>(tokens: &[StepToken], i: &mut usize, writer: &mut CubeWriter<W>) -> IoResult<bool> {
+    while *i < tokens.len() {
+        match &tokens[*i] {
+            StepToken::Bits(bits) => {
+                for &bit in bits {
+                    writer.write_bit(bit)?;
+                }
+            }
+            StepToken::Nop => writer.write_nop()?,
+            StepToken::Close => {
+                ensure_aligned(writer)?;
+                return Result::Ok(true);
+            }
+            token => {
+                ensure_aligned(writer)?;
+                write_token(token, tokens, i, writer)?;
+            }
+        }
+        *i += 1;
+    }
+    Result::Ok(false)
+}
+
+fn write_token<W: Write>(token: &StepToken, tokens: &[StepToken], i: &mut usize, writer: &mut CubeWriter<W>) -> VioResult {
+    match token {
+        StepToken::Byte(byte) => writer.write_uint8(*byte)?,
+        StepToken::StartZlib => {
+            *i += 1;
+            let mut inner = CubeWriter::new(Encoder::new(Vec::new()));
+            if !parse_until_end(tokens, i, &mut inner)? { io_error("Unclosed Z{ section")? }
+            let result = inner.target.finish().into_result()?;
+            writer.write_bytes(result.as_slice())?;
+        }
+        StepToken::Utf8String(str) => writer.write_bytes(str.as_bytes())?,
+        StepToken::Float(f) => writer.write_float32(*f)?,
+        StepToken::Double(f) => writer.write_float64(*f)?,
+        StepToken::Integer(int_type, value) => match int_type {
+            IntType::U8 => writer.write_uint8(*value as u8)?,
+            IntType::U16 => writer.write_uint16(*value as u16)?,
+            IntType::U32 => writer.write_uint32(*value as u32)?,
+            IntType::U64 => writer.write_uint64(*value as u64)?,
+            IntType::I8 => writer.write_int8(*value as i8)?,
+            IntType::I16 => writer.write_int16(*value as i16)?,
+            IntType::I32 => writer.write_int32(*value as i32)?,
+            IntType::I64 => writer.write_int64(*value as i64)?,
+            IntType::VarUint => writer.write_varuint(*value as u64)?,
+            IntType::VarInt => writer.write_varint(*value as i64)?,
+        },
+        StepToken::Str(str) => writer.write_string(str.as_str())?,
+        StepToken::Str32(str) => writer.write_string32(str.as_str())?,
+        StepToken::SignalId(_, SignalId::LowLevel(id)) => writer.write_uint8(*id)?,
+        StepToken::SignalId(_, SignalId::Packed(id)) => writer.write_uint16(*id)?,
+        StepToken::Bits(_) | StepToken::Nop | StepToken::Close => unreachable!(),
+    }
+    Result::Ok(())
+}
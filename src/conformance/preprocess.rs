@@ -0,0 +1,151 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::util::{io_error_f, IoResult};
+
+type Variables = HashMap<String, String>;
+
+/// The maximum number of nested `+ name` includes
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Reads a conversation file, stripping comments and blank lines
+/// and expanding `+ name` includes from `lib_dir/name.txt`
+pub fn preprocess_file(file: &Path, lib_dir: &Path) -> IoResult<Vec<String>> {
+    preprocess_file_with(file, lib_dir, Variables::new(), &mut Vec::new())
+}
+
+/// Strips comments and blank lines and expands includes from `lib_dir`
+pub fn preprocess_lines<I: Iterator<Item = String>>(input: I, lib_dir: &Path) -> IoResult<Vec<String>> {
+    preprocess_lines_with(input, lib_dir, Variables::new(), &mut Vec::new())
+}
+
+fn preprocess_file_with(file: &Path, lib_dir: &Path, arguments: Variables, includes: &mut Vec<String>) -> IoResult<Vec<String>> {
+    let fs = match File::open(file) {
+        Ok(f) => f,
+        Err(e) => io_error_f("Error pre-processing file ".to_owned() + file.to_str().unwrap_or("<unknown>") + ": " + e.to_string().as_str())?,
+    };
+    let mut lines = Vec::<String>::new();
+    for line in BufReader::new(fs).lines() {
+        lines.push(line?);
+    }
+    match preprocess_lines_with(lines.into_iter(), lib_dir, arguments, includes) {
+        Ok(lines) => Result::Ok(lines),
+        Err(e) => io_error_f(format!("{}: {}", file.to_str().unwrap_or("<unknown>"), e)),
+    }
+}
+
+/// Preprocesses lines with the arguments passed to an include.
+///
+/// `= name=value ...` lines declare the accepted parameters with their default values,
+/// and `${name}` is replaced with the value of a parameter in the lines that follow.
+/// `includes` is the stack of the names of the includes being expanded.
+fn preprocess_lines_with<I: Iterator<Item = String>>(input: I, lib_dir: &Path, mut arguments: Variables, includes: &mut Vec<String>) -> IoResult<Vec<String>> {
+    let mut lines = Vec::<String>::new();
+    let mut variables = Variables::new();
+
+    for line in input {
+        let line = line.trim();
+        if line.starts_with(';') || line.is_empty() { continue; }
+
+        if let Some(declarations) = line.strip_prefix('=') {
+            for (name, default) in parse_assignments(declarations.split_whitespace())? {
+                let value = arguments.remove(&name).unwrap_or(default);
+                variables.insert(name, value);
+            }
+            continue;
+        }
+
+        let line = substitute(line, &variables)?;
+
+        if let Some(include) = line.strip_prefix('+') {
+            let mut words = include.split_whitespace();
+            let name = match words.next() {
+                Some(name) => name,
+                None => return io_error_f("Missing include name".to_owned()),
+            };
+            if includes.iter().any(|included| included == name) {
+                return io_error_f(format!("Include cycle {} -> {}", includes.join(" -> "), name));
+            }
+            if includes.len() >= MAX_INCLUDE_DEPTH {
+                return io_error_f(format!("Includes nested deeper than {} levels at {}", MAX_INCLUDE_DEPTH, name));
+            }
+            let arguments = parse_assignments(words)?;
+            let path = lib_dir.join(name.to_owned() + ".txt");
+            includes.push(name.to_owned());
+            let inner = preprocess_file_with(&path, lib_dir, arguments.into_iter().collect(), includes);
+            includes.pop();
+            for inner in inner? {
+                lines.push(inner);
+            }
+            continue;
+        }
+
+        lines.push(line);
+    }
+
+    if let Some(name) = arguments.keys().min() {
+        return io_error_f(format!("Unknown include argument {}", name));
+    }
+
+    Result::Ok(lines)
+}
+
+/// Parses `name=value` words
+fn parse_assignments<'a, I: Iterator<Item = &'a str>>(words: I) -> IoResult<Vec<(String, String)>> {
+    let mut assignments = Vec::new();
+    for word in words {
+        match word.find('=') {
+            Some(index) if index > 0 => assignments.push((word[..index].to_owned(), word[index + 1..].to_owned())),
+            _ => return io_error_f(format!("Expected name=value, got {}", word)),
+        }
+    }
+    Result::Ok(assignments)
+}
+
+/// Replaces `${name}` with the value of the variable and `$$` with `$`
+fn substitute(line: &str, variables: &Variables) -> IoResult<String> {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(index) = rest.find('$') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            output.push('$');
+            rest = after;
+            continue;
+        }
+        let end = match rest.strip_prefix('{').and_then(|after| after.find('}')) {
+            Some(end) => end + 1,
+            None => return io_error_f(format!("Expected ${{name}} or $$ in {}", line)),
+        };
+        let name = &rest[1..end];
+        match variables.get(name) {
+            Some(value) => output.push_str(value),
+            None => return io_error_f(format!("Undefined variable {}", name)),
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Result::Ok(output)
+}
@@ -0,0 +1,102 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::Path;
+
+use crate::conformance::{Conversation, Step, StepToken};
+use crate::conformance::lexer::lex_step_line;
+use crate::conformance::preprocess::preprocess_file;
+use crate::util::{io_error, io_error_f, IoResult};
+
+/// Groups preprocessed lines into steps.
+///
+/// Yields one step for each line starting with `<` or `>`,
+/// including the buffer data on the following lines.
+pub struct Steps<I: Iterator<Item = String>> {
+    lines: std::iter::Peekable<I>,
+}
+
+impl<I: Iterator<Item = String>> Steps<I> {
+    pub fn new(lines: I) -> Steps<I> {
+        Steps { lines: lines.peekable() }
+    }
+}
+
+impl<I: Iterator<Item = String>> Iterator for Steps<I> {
+    type Item = IoResult<Step<StepToken>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.lines.next()?;
+        let from_server = if first.starts_with('<') {
+            true
+        } else if first.starts_with('>') {
+            false
+        } else {
+            return Some(io_error_f("Encountered buffer line without direction, on line \"".to_owned() + first.as_str() + "\""));
+        };
+        let mut step = Step { from_server, buffer: Vec::new() };
+        if let Err(err) = lex_step_line(first[1..].trim(), &mut step.buffer) {
+            return Some(Result::Err(err));
+        }
+        while let Some(line) = self.lines.peek() {
+            if line.starts_with('<') || line.starts_with('>') { break; }
+            if let Err(err) = lex_step_line(line, &mut step.buffer) {
+                return Some(Result::Err(err));
+            }
+            self.lines.next();
+        }
+        Some(Result::Ok(step))
+    }
+}
+
+/// Reads all `*.txt` conversations in `dir`, resolving includes from `dir/lib`.
+/// The conversations are sorted by name.
+pub fn read_conversations(dir: &Path) -> IoResult<Vec<Conversation>> {
+    let lib_dir = dir.join("lib");
+    let mut ret = Vec::<Conversation>::new();
+    for file in dir.read_dir()? {
+        let file = file?.path();
+        if !file.is_file() { continue; }
+        if file.extension().and_then(|ext| ext.to_str()) == Some("txt") {
+            ret.push(read_conversation_file(&file, &lib_dir)?);
+        }
+    }
+    ret.sort_by(|a, b| a.name.cmp(&b.name));
+    Result::Ok(ret)
+}
+
+/// Reads a conversation file, resolving includes from `lib_dir`.
+/// The conversation is named after the file stem.
+pub fn read_conversation_file(file: &Path, lib_dir: &Path) -> IoResult<Conversation> {
+    let name = match file.file_stem().and_then(|stem| stem.to_str()) {
+        Some(name) => name,
+        None => io_error("Bad filename")?,
+    };
+
+    let mut conversation = Conversation {
+        name: name.to_owned(),
+        steps: Vec::new(),
+    };
+
+    let lines = preprocess_file(file, lib_dir)?;
+    for step in Steps::new(lines.into_iter()) {
+        conversation.steps.push(step?.encode()?);
+    }
+    Result::Ok(conversation)
+}
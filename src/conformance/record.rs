@@ -0,0 +1,188 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::auth::AuthError;
+use crate::client::ClientAdapter;
+use crate::conformance::disassemble;
+use crate::protocol::ll::disconnect::DisconnectCode;
+use crate::protocol::ll::login_request::LoginRequest;
+use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::cube_dict::CubeDictSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::version::VersionRejection;
+use crate::server::SessionAdapter;
+use crate::text::Text;
+use crate::util::VioResult;
+
+/// A binary message captured by a `Recorder`
+pub struct RecordedFrame {
+    pub from_server: bool,
+    /// The time since the recorder was created
+    pub time: Duration,
+    pub buffer: Vec<u8>,
+}
+
+/// Wraps a `ClientAdapter` or `SessionAdapter` and captures every message sent or received through it.
+///
+/// The captured messages can be written out as a `tests/hex` conversation.
+pub struct Recorder<A> {
+    adapter: A,
+    start: Instant,
+    frames: Vec<RecordedFrame>,
+}
+
+impl<A> Recorder<A> {
+    pub fn new(adapter: A) -> Recorder<A> {
+        Recorder { adapter, start: Instant::now(), frames: Vec::new() }
+    }
+
+    pub fn adapter(&self) -> &A { &self.adapter }
+
+    pub fn adapter_mut(&mut self) -> &mut A { &mut self.adapter }
+
+    pub fn frames(&self) -> &[RecordedFrame] { &self.frames }
+
+    pub fn into_inner(self) -> (A, Vec<RecordedFrame>) { (self.adapter, self.frames) }
+
+    fn record(&mut self, from_server: bool, frame: &[u8]) {
+        self.frames.push(RecordedFrame { from_server, time: self.start.elapsed(), buffer: frame.to_vec() });
+    }
+
+    /// Writes the captured messages as a `tests/hex` conversation.
+    ///
+    /// Each step is preceded by a comment with its timestamp,
+    /// and LL_PACKAGE messages are written with their `Z{...}` sections expanded.
+    pub fn write_conversation<W: Write>(&self, name: &str, out: &mut W) -> VioResult {
+        writeln!(out, "; {}", name)?;
+        for frame in &self.frames {
+            let time = frame.time.as_secs_f64();
+            writeln!(out, "; {:.3}s", time)?;
+            writeln!(out, "{}", disassemble(&frame.buffer).into_step(frame.from_server))?;
+        }
+        Result::Ok(())
+    }
+}
+
+impl<A: ClientAdapter> ClientAdapter for Recorder<A> {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.record(false, &frame);
+        self.adapter.send(frame)
+    }
+
+    fn on_receive(&mut self, frame: &[u8]) {
+        self.record(true, frame);
+        self.adapter.on_receive(frame);
+    }
+
+    fn on_version_rejected(&mut self, rejection: &VersionRejection) {
+        self.adapter.on_version_rejected(rejection);
+    }
+
+    fn on_disconnect(&mut self, code: DisconnectCode, message: &str, rejoin: bool) {
+        self.adapter.on_disconnect(code, message, rejoin);
+    }
+
+    fn on_cube_dict(&mut self, signal: &CubeDictSignal) {
+        self.adapter.on_cube_dict(signal);
+    }
+
+    fn on_cube_batch(&mut self, signal: &CubeBatchSignal) {
+        self.adapter.on_cube_batch(signal);
+    }
+
+    fn on_cube_update(&mut self, signal: &CubeUpdateSignal) {
+        self.adapter.on_cube_update(signal);
+    }
+
+    fn on_flex_motion(&mut self, signal: &FlexMotionSignal) {
+        self.adapter.on_flex_motion(signal);
+    }
+
+    fn on_flex_flags(&mut self, signal: &FlexFlagsSignal) {
+        self.adapter.on_flex_flags(signal);
+    }
+
+    fn on_world_switch(&mut self, signal: &WorldSwitchSignal) {
+        self.adapter.on_world_switch(signal);
+    }
+}
+
+impl<A: SessionAdapter> SessionAdapter for Recorder<A> {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.record(true, &frame);
+        self.adapter.send(frame)
+    }
+
+    fn on_receive(&mut self, frame: &[u8]) {
+        self.record(false, frame);
+        self.adapter.on_receive(frame);
+    }
+
+    fn now(&self) -> Instant { self.adapter.now() }
+
+    fn on_version_rejected(&mut self, rejection: &VersionRejection) {
+        self.adapter.on_version_rejected(rejection);
+    }
+
+    fn on_auth_failed(&mut self, error: &AuthError) {
+        self.adapter.on_auth_failed(error);
+    }
+
+    fn on_login_request(&mut self, request: &LoginRequest) {
+        self.adapter.on_login_request(request);
+    }
+
+    fn on_login_accepted(&mut self, request: &LoginRequest) {
+        self.adapter.on_login_accepted(request);
+    }
+
+    fn on_login_rejected(&mut self, request: &LoginRequest, code: DisconnectCode, reason: &Text) {
+        self.adapter.on_login_rejected(request, code, reason);
+    }
+
+    fn on_cube_interact(&mut self, signal: &CubeInteractSignal) {
+        self.adapter.on_cube_interact(signal);
+    }
+
+    fn on_user_motion(&mut self, signal: &UserMotionSignal) {
+        self.adapter.on_user_motion(signal);
+    }
+
+    fn on_user_flags(&mut self, signal: &UserFlagsSignal) {
+        self.adapter.on_user_flags(signal);
+    }
+
+    fn on_timeout(&mut self) {
+        self.adapter.on_timeout();
+    }
+
+    fn on_disconnect(&mut self) {
+        self.adapter.on_disconnect();
+    }
+}
@@ -0,0 +1,218 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use std::path::Path;
+use std::time::Instant;
+
+use crate::auth::AuthError;
+use crate::client::{Backoff, Client, ClientAdapter, ClientConfig};
+use crate::conformance::{Conversation, lex_step_line, parse_step, Recorder};
+use crate::io::cube::IntPos;
+use crate::io::reader::CubeReader;
+use crate::protocol::capability::CapabilitySet;
+use crate::protocol::ll::disconnect::DisconnectCode;
+use crate::protocol::ll::login_request::LoginRequest;
+use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::cube_dict::CubeDictSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::version::{ProtocolVersion, VersionRejection};
+use crate::server::{Session, SessionAdapter};
+use crate::text::Text;
+use crate::util::VioResult;
+
+#[derive(Default)]
+struct Queue {
+    sent: Vec<Vec<u8>>,
+}
+
+impl ClientAdapter for Queue {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.sent.push(frame);
+        Result::Ok(())
+    }
+}
+
+impl SessionAdapter for Queue {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.sent.push(frame);
+        Result::Ok(())
+    }
+}
+
+fn spawn_package() -> Vec<u8> {
+    let mut tokens = Vec::new();
+    lex_step_line("Z{ bits{1} nop PK_SPAWN_SPAWN i32{0} i32{1} i32{0} F{8} F{0} F{8} F{0} F{0} bits{0} nop }", &mut tokens).unwrap();
+    let deflated = parse_step(&tokens).unwrap();
+    let mut frame = vec![0xe1];
+    frame.extend_from_slice(&(deflated.len() as u32).to_be_bytes());
+    frame.extend(deflated);
+    frame
+}
+
+fn record(recorder: &Recorder<Queue>) -> String {
+    let mut text = Vec::new();
+    recorder.write_conversation("recorded", &mut text).unwrap();
+    String::from_utf8(text).unwrap()
+}
+
+#[test]
+fn record_session() {
+    let config = ClientConfig {
+        username: "Alex".to_owned(),
+        user_id: [7; 20],
+        language: "en_GB".to_owned(),
+        sys_info: "{}".to_owned(),
+        version: ProtocolVersion::CURRENT,
+        capabilities: CapabilitySet::EMPTY,
+        credentials: None,
+        backoff: Backoff::default(),
+    };
+    let mut client = Client::new(Recorder::new(Queue::default()), config);
+    let mut session = Session::new(Recorder::new(Queue::default()));
+
+    client.connect().unwrap();
+    session.receive(&client.adapter_mut().adapter_mut().sent.remove(0)).unwrap();
+    session.accept().unwrap();
+    client.receive(&session.adapter_mut().adapter_mut().sent.remove(0)).unwrap();
+    client.receive(&spawn_package()).unwrap();
+
+    let client_text = record(client.adapter());
+    let lines: Vec<&str> = client_text.lines().collect();
+    assert_eq!(lines[0], "; recorded");
+    assert!(lines[1].starts_with("; ") && lines[1].ends_with('s'));
+    assert!(lines[2].starts_with("> LL_LOGIN_REQUEST u32{1} u32{6} str{Alex} 07 07"));
+    assert_eq!(lines[4], "< LL_LOGIN_ACCEPT u32{6} u64{0} u16{0} bits{0} nop");
+    assert!(lines[6].starts_with("< LL_PACKAGE u32{"));
+    assert!(lines[6].ends_with("Z{ bits{1} nop PK_SPAWN_SPAWN i32{0} i32{1} i32{0} F{8.0} F{0.0} F{8.0} F{0.0} F{0.0} bits{0} nop }"));
+
+    let conversation = Conversation::parse("recorded", &client_text, Path::new("tests/hex/lib")).unwrap();
+    let frames = client.adapter().frames();
+    assert_eq!(conversation.steps.len(), frames.len());
+    for (step, frame) in conversation.steps.iter().zip(frames) {
+        assert_eq!(step.from_server, frame.from_server);
+        assert_eq!(step.buffer, frame.buffer);
+    }
+
+    let session_text = record(session.adapter());
+    let conversation = Conversation::parse("recorded", &session_text, Path::new("tests/hex/lib")).unwrap();
+    assert_eq!(conversation.steps.len(), 2);
+    assert!(!conversation.steps[0].from_server);
+    assert!(conversation.steps[1].from_server);
+    assert_eq!(conversation.steps[0].buffer, frames[0].buffer);
+    assert_eq!(conversation.steps[1].buffer, frames[1].buffer);
+}
+
+/// Reads a signal from zeroes, for tests that only need some value of the signal
+macro_rules! zeroed {
+    ($signal: ty) => {
+        <$signal>::read(&mut CubeReader::new(&[0u8; 64][..])).unwrap()
+    }
+}
+
+/// Lists the adapter callbacks that it receives
+struct Calls {
+    calls: Vec<&'static str>,
+    now: Instant,
+}
+
+#[allow(unused_variables)]
+impl ClientAdapter for Calls {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.calls.push("send");
+        Result::Ok(())
+    }
+    fn on_receive(&mut self, frame: &[u8]) { self.calls.push("on_receive"); }
+    fn on_version_rejected(&mut self, rejection: &VersionRejection) { self.calls.push("on_version_rejected"); }
+    fn on_disconnect(&mut self, code: DisconnectCode, message: &str, rejoin: bool) { self.calls.push("on_disconnect"); }
+    fn on_cube_dict(&mut self, signal: &CubeDictSignal) { self.calls.push("on_cube_dict"); }
+    fn on_cube_batch(&mut self, signal: &CubeBatchSignal) { self.calls.push("on_cube_batch"); }
+    fn on_cube_update(&mut self, signal: &CubeUpdateSignal) { self.calls.push("on_cube_update"); }
+    fn on_flex_motion(&mut self, signal: &FlexMotionSignal) { self.calls.push("on_flex_motion"); }
+    fn on_flex_flags(&mut self, signal: &FlexFlagsSignal) { self.calls.push("on_flex_flags"); }
+    fn on_world_switch(&mut self, signal: &WorldSwitchSignal) { self.calls.push("on_world_switch"); }
+}
+
+#[allow(unused_variables)]
+impl SessionAdapter for Calls {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.calls.push("send");
+        Result::Ok(())
+    }
+    fn on_receive(&mut self, frame: &[u8]) { self.calls.push("on_receive"); }
+    fn now(&self) -> Instant { self.now }
+    fn on_version_rejected(&mut self, rejection: &VersionRejection) { self.calls.push("on_version_rejected"); }
+    fn on_auth_failed(&mut self, error: &AuthError) { self.calls.push("on_auth_failed"); }
+    fn on_login_request(&mut self, request: &LoginRequest) { self.calls.push("on_login_request"); }
+    fn on_login_accepted(&mut self, request: &LoginRequest) { self.calls.push("on_login_accepted"); }
+    fn on_login_rejected(&mut self, request: &LoginRequest, code: DisconnectCode, reason: &Text) { self.calls.push("on_login_rejected"); }
+    fn on_cube_interact(&mut self, signal: &CubeInteractSignal) { self.calls.push("on_cube_interact"); }
+    fn on_user_motion(&mut self, signal: &UserMotionSignal) { self.calls.push("on_user_motion"); }
+    fn on_user_flags(&mut self, signal: &UserFlagsSignal) { self.calls.push("on_user_flags"); }
+    fn on_timeout(&mut self) { self.calls.push("on_timeout"); }
+    fn on_disconnect(&mut self) { self.calls.push("on_disconnect"); }
+}
+
+#[test]
+fn forward_callbacks() {
+    let rejection = VersionRejection::ClientOutdated { client: ProtocolVersion::new(1, 0), server: ProtocolVersion::CURRENT };
+    let request = zeroed!(LoginRequest);
+
+    let mut client = Recorder::new(Calls { calls: Vec::new(), now: Instant::now() });
+    ClientAdapter::send(&mut client, vec![0x21]).unwrap();
+    ClientAdapter::on_receive(&mut client, &[0x41]);
+    ClientAdapter::on_version_rejected(&mut client, &rejection);
+    ClientAdapter::on_disconnect(&mut client, DisconnectCode::Kicked, "Bye", false);
+    client.on_cube_dict(&CubeDictSignal { defs: Vec::new() });
+    client.on_cube_batch(&CubeBatchSignal { pos: IntPos::new(0, 0, 0), payload: [0; 4096] });
+    client.on_cube_update(&zeroed!(CubeUpdateSignal));
+    client.on_flex_motion(&zeroed!(FlexMotionSignal));
+    client.on_flex_flags(&zeroed!(FlexFlagsSignal));
+    client.on_world_switch(&zeroed!(WorldSwitchSignal));
+    assert_eq!(client.adapter().calls, vec![
+        "send", "on_receive", "on_version_rejected", "on_disconnect",
+        "on_cube_dict", "on_cube_batch", "on_cube_update", "on_flex_motion", "on_flex_flags", "on_world_switch",
+    ]);
+
+    let now = Instant::now();
+    let mut session = Recorder::new(Calls { calls: Vec::new(), now });
+    SessionAdapter::send(&mut session, vec![0x41]).unwrap();
+    SessionAdapter::on_receive(&mut session, &[0x21]);
+    assert_eq!(SessionAdapter::now(&session), now);
+    SessionAdapter::on_version_rejected(&mut session, &rejection);
+    session.on_auth_failed(&AuthError::BadSignature);
+    session.on_login_request(&request);
+    session.on_login_accepted(&request);
+    session.on_login_rejected(&request, DisconnectCode::Banned, &Text::literal("Banned"));
+    session.on_cube_interact(&zeroed!(CubeInteractSignal));
+    session.on_user_motion(&zeroed!(UserMotionSignal));
+    session.on_user_flags(&zeroed!(UserFlagsSignal));
+    session.on_timeout();
+    SessionAdapter::on_disconnect(&mut session);
+    assert_eq!(session.adapter().calls, vec![
+        "send", "on_receive", "on_version_rejected", "on_auth_failed", "on_login_request", "on_login_accepted",
+        "on_login_rejected", "on_cube_interact", "on_user_motion", "on_user_flags", "on_timeout", "on_disconnect",
+    ]);
+}
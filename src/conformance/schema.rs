@@ -0,0 +1,267 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use lazy_static::lazy_static;
+
+use crate::conformance::ids::{resolve_signal_id, SignalId};
+use crate::util::{io_error_f, IoResult};
+
+const SPEC: &str = include_str!("../../protocol/spec.txt");
+
+/// The sections of `protocol/spec.txt` that describe signals
+const SIGNAL_SECTIONS: &[&str] = &["HandShake", "Authenticate", "LoginAccept", "Disconnect", "Ping", "Load", "Spawn", "GamePlay", "WorldSwitch"];
+
+lazy_static! {
+    static ref SPEC_SCHEMA: Schema = Schema::parse(SPEC).expect("protocol/spec.txt is malformed");
+}
+
+/// The wire format described by `protocol/spec.txt`
+pub struct Schema {
+    /// The structs in `[DataTypes]`
+    pub structs: Vec<SchemaStruct>,
+    pub signals: Vec<SchemaSignal>,
+}
+
+pub struct SchemaStruct {
+    pub name: String,
+    pub items: Vec<SchemaItem>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// `CS`, client to server only
+    ClientToServer,
+    /// `SC`, server to client only
+    ServerToClient,
+    /// `MT`, sent from both sides
+    Mutual,
+}
+
+pub struct SchemaSignal {
+    /// The spec section that describes the signal, e.g. `GamePlay`
+    pub section: String,
+    pub direction: Direction,
+    /// The name in the spec, e.g. `FLEX_MOTION`
+    pub name: String,
+    /// The ID in `ll-id.txt` or `pk-id.txt`, if listed
+    pub id: Option<SignalId>,
+    pub items: Vec<SchemaItem>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaItem {
+    /// `name type`. The name may be indexed by a loop variable, e.g. `cubeDefId[i]`.
+    Field { name: String, field_type: SchemaType },
+    Nop,
+    /// A part of the format that is not specified yet
+    Todo,
+    /// `for index from 1 to count`, where `count` is a previous field
+    Repeat { index: String, count: String, items: Vec<SchemaItem> },
+    /// `if field does not start with "prefix"`
+    UnlessPrefix { field: String, prefix: String, items: Vec<SchemaItem> },
+    /// `if field >= min`, where `field` is a previous integer field
+    IfAtLeast { field: String, min: u64, items: Vec<SchemaItem> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaType {
+    /// `[u]int(8|16|32|64)`
+    Int { signed: bool, bits: u8 },
+    Float32,
+    Float64,
+    Bool,
+    Nibble,
+    /// `bits(n)`
+    Bits(u8),
+    Byte,
+    String,
+    String32,
+    /// `type[length]`
+    Array(Box<SchemaType>, ArrayLength),
+    /// A struct in `[DataTypes]`
+    Struct(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrayLength {
+    Fixed(usize),
+    /// The value of a previous field
+    Field(String),
+}
+
+impl Schema {
+    /// The schema of `protocol/spec.txt`
+    pub fn spec() -> &'static Schema { &SPEC_SCHEMA }
+
+    pub fn parse(text: &str) -> IoResult<Schema> {
+        let mut schema = Schema { structs: Vec::new(), signals: Vec::new() };
+        let mut section = "";
+        let mut lines = text.lines().enumerate().peekable();
+        while let Some((number, line)) = lines.next() {
+            if line.starts_with('[') && line.ends_with(']') {
+                section = &line[1..line.len() - 1];
+                continue;
+            }
+            let mut body = Vec::new();
+            while let Some(&(number, inner)) = lines.peek() {
+                if !inner.starts_with('\t') { break; }
+                body.push((number, inner));
+                lines.next();
+            }
+            let mut words = line.split_whitespace();
+            if section == "DataTypes" && words.next() == Some("struct") {
+                let name = words.next().unwrap_or("").to_owned();
+                schema.structs.push(SchemaStruct { name, items: parse_items(&body, 1)? });
+            } else if SIGNAL_SECTIONS.contains(&section) {
+                let direction = match line.split_whitespace().next() {
+                    Some("CS") => Direction::ClientToServer,
+                    Some("SC") => Direction::ServerToClient,
+                    Some("MT") => Direction::Mutual,
+                    _ if line.trim().is_empty() => continue,
+                    _ => return io_error_f(format!("Line {}: Expected a signal, got {}", number + 1, line)),
+                };
+                let name = line.split_whitespace().nth(1).unwrap_or("").to_owned();
+                let id = resolve_id(section, direction, &name)?;
+                schema.signals.push(SchemaSignal { section: section.to_owned(), direction, name, id, items: parse_items(&body, 1)? });
+            }
+        }
+        Result::Ok(schema)
+    }
+
+    pub fn find_struct(&self, name: &str) -> Option<&SchemaStruct> {
+        self.structs.iter().find(|schema| schema.name == name)
+    }
+
+    pub fn find_signal(&self, id: SignalId) -> Option<&SchemaSignal> {
+        self.signals.iter().find(|signal| signal.id == Some(id))
+    }
+}
+
+/// Resolves the ID of a signal by the naming convention of `ll-id.txt` and `pk-id.txt`,
+/// e.g. `LL_LOGIN_REQUEST`, `LL_SERVER_DISCONNECT` and `PK_GP_FLEX_MOTION`
+fn resolve_id(section: &str, direction: Direction, name: &str) -> IoResult<Option<SignalId>> {
+    let names = match section {
+        "Load" => vec![format!("PK_LOAD_{}", name)],
+        "Spawn" => vec![format!("PK_SPAWN_{}", name)],
+        "GamePlay" => vec![format!("PK_GP_{}", name)],
+        "WorldSwitch" => vec![format!("PK_{}", name)],
+        _ => vec![
+            format!("LL_{}", name),
+            format!("LL_{}_{}", if direction == Direction::ServerToClient { "SERVER" } else { "CLIENT" }, name),
+        ],
+    };
+    for name in names {
+        if let Some(id) = resolve_signal_id(&name) {
+            return Result::Ok(Some(id));
+        }
+    }
+    Result::Ok(None)
+}
+
+/// Parses the lines indented by at least `depth` tabs
+fn parse_items(lines: &[(usize, &str)], depth: usize) -> IoResult<Vec<SchemaItem>> {
+    let mut items = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let (number, line) = lines[i];
+        i += 1;
+        let text = line.split(';').next().unwrap_or("").trim();
+        if text.is_empty() { continue; }
+        if line.len() - line.trim_start_matches('\t').len() != depth {
+            return io_error_f(format!("Line {}: Unexpected indentation", number + 1));
+        }
+        let start = i;
+        while i < lines.len() && lines[i].1.starts_with(&"\t".repeat(depth + 1)) {
+            i += 1;
+        }
+        let children = &lines[start..i];
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let item = match words.as_slice() {
+            ["nop"] => SchemaItem::Nop,
+            ["TODO"] => SchemaItem::Todo,
+            ["for", index, "from", "1", "to", count] => SchemaItem::Repeat {
+                index: (*index).to_owned(),
+                count: (*count).to_owned(),
+                items: parse_items(children, depth + 1)?,
+            },
+            ["if", field, "does", "not", "start", "with", prefix] if prefix.len() >= 2 && prefix.starts_with('"') && prefix.ends_with('"') => SchemaItem::UnlessPrefix {
+                field: (*field).to_owned(),
+                prefix: prefix[1..prefix.len() - 1].to_owned(),
+                items: parse_items(children, depth + 1)?,
+            },
+            ["if", field, ">=", min] if min.parse::<u64>().is_ok() => SchemaItem::IfAtLeast {
+                field: (*field).to_owned(),
+                min: min.parse().expect("checked by the guard"),
+                items: parse_items(children, depth + 1)?,
+            },
+            [name, field_type] => SchemaItem::Field { name: (*name).to_owned(), field_type: parse_type(field_type) },
+            _ => return io_error_f(format!("Line {}: Unexpected {}", number + 1, text)),
+        };
+        if !children.is_empty() && !matches!(item, SchemaItem::Repeat { .. } | SchemaItem::UnlessPrefix { .. } | SchemaItem::IfAtLeast { .. }) {
+            return io_error_f(format!("Line {}: Unexpected indentation", children[0].0 + 1));
+        }
+        items.push(item);
+    }
+    Result::Ok(items)
+}
+
+fn parse_type(name: &str) -> SchemaType {
+    if name.ends_with(']') {
+        if let Some(open) = name.find('[') {
+            let length = &name[open + 1..name.len() - 1];
+            let length = match length.parse() {
+                Ok(length) => ArrayLength::Fixed(length),
+                Err(_) => ArrayLength::Field(length.to_owned()),
+            };
+            return SchemaType::Array(Box::new(parse_type(&name[..open])), length);
+        }
+    }
+    if let Some(bits) = name.strip_prefix("bits(").and_then(|rest| rest.strip_suffix(')')).and_then(|bits| bits.parse().ok()) {
+        return SchemaType::Bits(bits);
+    }
+    match name {
+        "int8" => SchemaType::Int { signed: true, bits: 8 },
+        "int16" => SchemaType::Int { signed: true, bits: 16 },
+        "int32" => SchemaType::Int { signed: true, bits: 32 },
+        "int64" => SchemaType::Int { signed: true, bits: 64 },
+        "uint8" => SchemaType::Int { signed: false, bits: 8 },
+        "uint16" => SchemaType::Int { signed: false, bits: 16 },
+        "uint32" => SchemaType::Int { signed: false, bits: 32 },
+        "uint64" => SchemaType::Int { signed: false, bits: 64 },
+        "float32" => SchemaType::Float32,
+        "float64" => SchemaType::Float64,
+        "bool" => SchemaType::Bool,
+        "nibble" => SchemaType::Nibble,
+        "byte" => SchemaType::Byte,
+        "string" => SchemaType::String,
+        "string32" => SchemaType::String32,
+        _ => SchemaType::Struct(name.to_owned()),
+    }
+}
+
+/// Replaces the loop variables in an indexed field name, e.g. `cubeDefId[i]` with `cubeDefId[1]`
+pub fn substitute_indices(name: &str, indices: &[(String, usize)]) -> String {
+    let mut name = name.to_owned();
+    for (index, value) in indices {
+        name = name.replace(&format!("[{}]", index), &format!("[{}]", value));
+    }
+    name
+}
@@ -0,0 +1,303 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use std::collections::HashMap;
+
+use crate::conformance::{ArrayLength, Direction, Schema, SchemaItem, SchemaSignal, SchemaType, SignalId};
+use crate::conformance::schema::substitute_indices;
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::protocol::handler::SignalHandler;
+use crate::protocol::ll::auth::{AuthChallenge, AuthResponse};
+use crate::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect, ServerDisconnectReason};
+use crate::protocol::ll::handle_ll_slice;
+use crate::protocol::ll::login_accept::LoginAccept;
+use crate::protocol::ll::login_request::LoginRequest;
+use crate::protocol::ll::ping::{Ping, Pong};
+use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::cube_dict::CubeDictSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::pk::handle_pk_slice;
+use crate::protocol::pk::spawn::SpawnSignal;
+use crate::protocol::version::ProtocolVersion;
+use crate::util::{io_error, io_error_f, IoResult, VioResult};
+
+/// Field that selects the signal revision, sampled with the minor version being checked
+const VERSION_FIELD: &str = "minorProtocol";
+
+/// Signals in spec.txt that are not implemented yet
+const UNIMPLEMENTED: &[&str] = &["USER_ROTATION"];
+
+/// Writes every signal it handles back to `writer`
+struct Echo {
+    writer: CubeWriter<Vec<u8>>,
+}
+
+impl SignalHandler for Echo {
+    fn handle_ll_login_request(&mut self, signal: LoginRequest) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_auth_challenge(&mut self, signal: AuthChallenge) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_auth_response(&mut self, signal: AuthResponse) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_login_accept(&mut self, signal: LoginAccept) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_server_disconnect_reason(&mut self, signal: ServerDisconnectReason) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_ping(&mut self, signal: Ping) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_ll_pong(&mut self, signal: Pong) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_spawn(&mut self, signal: SpawnSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_flex_motion(&mut self, signal: FlexMotionSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_cube_update(&mut self, signal: CubeUpdateSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_cube_interact(&mut self, signal: CubeInteractSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_user_motion(&mut self, signal: UserMotionSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_user_flags(&mut self, signal: UserFlagsSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_flex_flags(&mut self, signal: FlexFlagsSignal) -> VioResult { signal.write(&mut self.writer) }
+    fn handle_pk_world_switch(&mut self, signal: WorldSwitchSignal) -> VioResult { signal.write(&mut self.writer) }
+}
+
+/// Writes a sample of a schema with a distinct value in each field except the version
+struct Sample<'s> {
+    schema: &'s Schema,
+    writer: CubeWriter<Vec<u8>>,
+    minor: u32,
+    counter: u64,
+    indices: Vec<(String, usize)>,
+    ints: HashMap<String, u64>,
+    strings: HashMap<String, String>,
+}
+
+impl<'s> Sample<'s> {
+    fn new(schema: &'s Schema, minor: u32) -> Sample<'s> {
+        Sample {
+            schema,
+            writer: CubeWriter::new(Vec::new()),
+            minor,
+            counter: 0,
+            indices: Vec::new(),
+            ints: HashMap::new(),
+            strings: HashMap::new(),
+        }
+    }
+
+    fn items(&mut self, items: &[SchemaItem]) -> VioResult {
+        for item in items {
+            match item {
+                SchemaItem::Field { name, field_type } => {
+                    let name = substitute_indices(name, &self.indices);
+                    self.value(&name, field_type)?;
+                }
+                SchemaItem::Nop => self.writer.write_nop()?,
+                SchemaItem::Todo => return io_error("Cannot sample an unspecified format"),
+                SchemaItem::Repeat { index, count, items } => {
+                    for i in 1..=self.ints[count] as usize {
+                        self.indices.push((index.clone(), i));
+                        self.items(items)?;
+                        self.indices.pop();
+                    }
+                }
+                SchemaItem::UnlessPrefix { field, prefix, items } => {
+                    if !self.strings[&substitute_indices(field, &self.indices)].starts_with(prefix.as_str()) {
+                        self.items(items)?;
+                    }
+                }
+                SchemaItem::IfAtLeast { field, min, items } => {
+                    if self.ints[&substitute_indices(field, &self.indices)] >= *min {
+                        self.items(items)?;
+                    }
+                }
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn value(&mut self, name: &str, field_type: &SchemaType) -> VioResult {
+        self.counter += 1;
+        let n = if name == VERSION_FIELD { self.minor as u64 } else { self.counter };
+        match field_type {
+            SchemaType::Int { signed, bits } => {
+                self.ints.insert(name.to_owned(), n);
+                let value = if *signed { (n as i64).wrapping_neg() as u64 } else { n };
+                match bits {
+                    8 => self.writer.write_uint8(value as u8)?,
+                    16 => self.writer.write_uint16(value as u16)?,
+                    32 => self.writer.write_uint32(value as u32)?,
+                    64 => self.writer.write_uint64(value)?,
+                    _ => return io_error("Unsupported integer width"),
+                }
+            }
+            SchemaType::Float32 => self.writer.write_float32(n as f32 + 0.5)?,
+            SchemaType::Float64 => self.writer.write_float64(n as f64 + 0.5)?,
+            SchemaType::Bool => self.writer.write_bit(n & 1 == 1)?,
+            SchemaType::Nibble => self.writer.write_bits(4, n & 0xF)?,
+            SchemaType::Bits(width) => self.writer.write_bits(*width, n & (u64::MAX >> (64 - width)))?,
+            SchemaType::Byte => self.writer.write_uint8(n as u8)?,
+            SchemaType::String | SchemaType::String32 => {
+                // Names of built-in cubes do not have cube models, which are not specified yet
+                let value = format!("CubePump.{}", n);
+                if *field_type == SchemaType::String {
+                    self.writer.write_string(&value)?;
+                } else {
+                    self.writer.write_string32(&value)?;
+                }
+                self.strings.insert(name.to_owned(), value);
+            }
+            SchemaType::Array(element, length) => {
+                let length = match length {
+                    ArrayLength::Fixed(length) => *length,
+                    ArrayLength::Field(field) => self.ints[field] as usize,
+                };
+                for i in 0..length {
+                    self.value(&format!("{}[{}]", name, i), element)?;
+                }
+            }
+            SchemaType::Struct(name) => {
+                let schema = self.schema;
+                match schema.find_struct(name) {
+                    Some(schema) => self.items(&schema.items)?,
+                    None => return io_error_f(format!("Unknown type {}", name)),
+                }
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+fn sample(schema: &Schema, signal: &SchemaSignal, minor: u32) -> IoResult<Vec<u8>> {
+    let mut sample = Sample::new(schema, minor);
+    match signal.id {
+        Some(SignalId::LowLevel(id)) => sample.writer.write_uint8(id)?,
+        Some(SignalId::Packed(id)) => sample.writer.write_uint16(id)?,
+        None => return io_error("Signal has no ID"),
+    }
+    sample.items(&signal.items)?;
+    Result::Ok(sample.writer.target)
+}
+
+/// Decodes a sample of the signal and checks that it is consumed completely and encoded back to the same bytes
+fn check_codec(schema: &Schema, signal: &SchemaSignal, minor: u32) -> VioResult {
+    let sample = sample(schema, signal, minor)?;
+    let mut echo = Echo { writer: CubeWriter::new(Vec::new()) };
+    let mut reader = CubeReader::new(sample.as_slice());
+    match signal.id {
+        Some(SignalId::LowLevel(_)) => handle_ll_slice(&mut echo, &mut reader)?,
+        _ => handle_pk_slice(&mut echo, &mut reader)?,
+    }
+    if reader.remaining() > 0 {
+        return io_error_f(format!("{} bytes were not read", reader.remaining()));
+    }
+    if echo.writer.target != sample {
+        return io_error_f(format!("Encoded {:02x?}, expected {:02x?}", echo.writer.target, sample));
+    }
+    Result::Ok(())
+}
+
+#[test]
+fn codecs_match_spec() {
+    let schema = Schema::spec();
+    let mut unimplemented = Vec::new();
+    for signal in &schema.signals {
+        if signal.id.is_none() || UNIMPLEMENTED.contains(&signal.name.as_str()) {
+            unimplemented.push(signal.name.as_str());
+            continue;
+        }
+        for minor in 0..=ProtocolVersion::CURRENT.minor {
+            if let Err(err) = check_codec(schema, signal, minor) {
+                panic!("{} {} does not match spec.txt at minor {}: {}", signal.section, signal.name, minor, err);
+            }
+        }
+    }
+    assert_eq!(unimplemented, UNIMPLEMENTED, "Update UNIMPLEMENTED to check the new signals");
+}
+
+#[test]
+fn codec_mismatch() {
+    let spec = include_str!("../../protocol/spec.txt");
+    let changed = Schema::parse(&spec.replace("minorProtocol uint32 ; to let", "minorProtocol uint16 ; to let")).unwrap();
+    let signal = changed.signals.iter().find(|signal| signal.name == "LOGIN_ACCEPT").unwrap();
+    assert!(check_codec(&changed, signal, ProtocolVersion::CURRENT.minor).is_err());
+
+    let gated = Schema::parse(&spec.replace("\t\tresumed bool\r\n\t\tnop\r\n", "\t\tresumed uint8\r\n")).unwrap();
+    let signal = gated.signals.iter().find(|signal| signal.name == "LOGIN_ACCEPT").unwrap();
+    assert!(check_codec(&gated, signal, 3).is_ok());
+    assert!(check_codec(&gated, signal, ProtocolVersion::CURRENT.minor).is_err());
+
+    let reordered = Schema::parse(&spec.replace("\treason string\r\n\trejoin bool\r\n", "\trejoin bool\r\n\tnop\r\n\treason string\r\n")).unwrap();
+    let signal = reordered.signals.iter().find(|signal| signal.name == "DISCONNECT").unwrap();
+    assert!(check_codec(&reordered, signal, ProtocolVersion::CURRENT.minor).is_err());
+}
+
+#[test]
+fn parse_spec() {
+    let schema = Schema::spec();
+    let ids: Vec<(&str, Direction, Option<SignalId>)> = schema.signals.iter()
+        .map(|signal| (signal.name.as_str(), signal.direction, signal.id))
+        .collect();
+    assert_eq!(ids[..9], [
+        ("LOGIN_REQUEST", Direction::ClientToServer, Some(SignalId::LowLevel(0x21))),
+        ("AUTH_CHALLENGE", Direction::ServerToClient, Some(SignalId::LowLevel(0x22))),
+        ("AUTH_RESPONSE", Direction::ClientToServer, Some(SignalId::LowLevel(0x23))),
+        ("LOGIN_ACCEPT", Direction::ServerToClient, Some(SignalId::LowLevel(0x41))),
+        ("DISCONNECT", Direction::ServerToClient, Some(SignalId::LowLevel(0x61))),
+        ("DISCONNECT_REASON", Direction::ServerToClient, Some(SignalId::LowLevel(0x63))),
+        ("DISCONNECT", Direction::ClientToServer, Some(SignalId::LowLevel(0x62))),
+        ("PING", Direction::Mutual, Some(SignalId::LowLevel(0x81))),
+        ("PONG", Direction::Mutual, Some(SignalId::LowLevel(0x82))),
+    ]);
+    assert!(ids.contains(&("FLEX_MOTION", Direction::ServerToClient, Some(SignalId::Packed(0x0311)))));
+    assert!(ids.contains(&("USER_ROTATION", Direction::ClientToServer, None)));
+
+    let flex_pos = schema.find_struct("flex_pos").unwrap();
+    assert_eq!(flex_pos.items, vec![
+        SchemaItem::Field { name: "batch".to_owned(), field_type: SchemaType::Struct("int_pos".to_owned()) },
+        SchemaItem::Field { name: "local".to_owned(), field_type: SchemaType::Struct("float_pos".to_owned()) },
+        SchemaItem::Field { name: "yaw".to_owned(), field_type: SchemaType::Float32 },
+        SchemaItem::Field { name: "pitch".to_owned(), field_type: SchemaType::Float32 },
+    ]);
+
+    let cube_dict = schema.find_signal(SignalId::Packed(0x0101)).unwrap();
+    assert_eq!(cube_dict.items[0], SchemaItem::Field { name: "size".to_owned(), field_type: SchemaType::Int { signed: false, bits: 32 } });
+    match &cube_dict.items[1] {
+        SchemaItem::Repeat { index, count, items } => {
+            assert_eq!((index.as_str(), count.as_str(), items.len()), ("i", "size", 3));
+            assert!(matches!(&items[2], SchemaItem::UnlessPrefix { prefix, .. } if prefix == "CubePump."));
+        }
+        item => panic!("Expected a loop, got {:?}", item),
+    }
+
+    let login_accept = schema.find_signal(SignalId::LowLevel(0x41)).unwrap();
+    assert_eq!(login_accept.items[1], SchemaItem::IfAtLeast {
+        field: "minorProtocol".to_owned(),
+        min: 2,
+        items: vec![SchemaItem::Field { name: "capabilities".to_owned(), field_type: SchemaType::Int { signed: false, bits: 64 } }],
+    });
+}
+
+#[test]
+fn parse_errors() {
+    assert!(Schema::parse("[Ping]\nXX PING\n").is_err());
+    assert!(Schema::parse("[Ping]\nMT PING\n\t\tlastCycle uint64\n").is_err());
+    assert!(Schema::parse("[Ping]\nMT PING\n\tlastCycle uint64 extra\n").is_err());
+}
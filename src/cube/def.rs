@@ -17,6 +17,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct CubeDef {
     pub id: u32,
     pub name: String,
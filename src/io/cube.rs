@@ -17,18 +17,159 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// The number of cubes along each axis of a batch
+pub const BATCH_SIZE: i32 = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct IntPos {
     pub x: i32,
     pub y: i32,
     pub z: i32,
 }
 
+impl IntPos {
+    pub fn new(x: i32, y: i32, z: i32) -> IntPos {
+        IntPos { x, y, z }
+    }
+
+    /// Returns the squared euclidean distance to `other`
+    pub fn distance_squared(&self, other: &IntPos) -> i64 {
+        let dx = (self.x as i64) - (other.x as i64);
+        let dy = (self.y as i64) - (other.y as i64);
+        let dz = (self.z as i64) - (other.z as i64);
+        dx * dx + dy * dy + dz * dz
+    }
+    /// Returns the sum of absolute differences along each axis
+    pub fn manhattan_distance(&self, other: &IntPos) -> i64 {
+        ((self.x as i64) - (other.x as i64)).abs()
+            + ((self.y as i64) - (other.y as i64)).abs()
+            + ((self.z as i64) - (other.z as i64)).abs()
+    }
+    /// Returns the largest absolute difference along any axis
+    pub fn chebyshev_distance(&self, other: &IntPos) -> i64 {
+        ((self.x as i64) - (other.x as i64)).abs()
+            .max(((self.y as i64) - (other.y as i64)).abs())
+            .max(((self.z as i64) - (other.z as i64)).abs())
+    }
+
+    pub fn to_float(&self) -> FloatPos {
+        FloatPos { x: self.x as f32, y: self.y as f32, z: self.z as f32 }
+    }
+}
+
+// IntPos arithmetic wraps around the i32 range instead of panicking,
+// since any coordinates can be received from the wire.
+
+impl Add for IntPos {
+    type Output = IntPos;
+    fn add(self, rhs: IntPos) -> IntPos { IntPos::new(self.x.wrapping_add(rhs.x), self.y.wrapping_add(rhs.y), self.z.wrapping_add(rhs.z)) }
+}
+
+impl Sub for IntPos {
+    type Output = IntPos;
+    fn sub(self, rhs: IntPos) -> IntPos { IntPos::new(self.x.wrapping_sub(rhs.x), self.y.wrapping_sub(rhs.y), self.z.wrapping_sub(rhs.z)) }
+}
+
+impl Neg for IntPos {
+    type Output = IntPos;
+    fn neg(self) -> IntPos { IntPos::new(self.x.wrapping_neg(), self.y.wrapping_neg(), self.z.wrapping_neg()) }
+}
+
+impl Mul<i32> for IntPos {
+    type Output = IntPos;
+    fn mul(self, rhs: i32) -> IntPos { IntPos::new(self.x.wrapping_mul(rhs), self.y.wrapping_mul(rhs), self.z.wrapping_mul(rhs)) }
+}
+
+impl AddAssign for IntPos {
+    fn add_assign(&mut self, rhs: IntPos) { *self = *self + rhs; }
+}
+
+impl SubAssign for IntPos {
+    fn sub_assign(&mut self, rhs: IntPos) { *self = *self - rhs; }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct FloatPos {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
+impl FloatPos {
+    pub fn new(x: f32, y: f32, z: f32) -> FloatPos {
+        FloatPos { x, y, z }
+    }
+
+    pub fn dot(&self, other: &FloatPos) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+    pub fn distance(&self, other: &FloatPos) -> f32 {
+        (*self - *other).length()
+    }
+    pub fn distance_squared(&self, other: &FloatPos) -> f32 {
+        let diff = *self - *other;
+        diff.dot(&diff)
+    }
+    /// Returns the vector scaled to unit length, or the zero vector if the length is zero
+    pub fn normalized(&self) -> FloatPos {
+        let length = self.length();
+        if length == 0.0 { FloatPos::default() } else { *self * (1.0 / length) }
+    }
+
+    /// Rounds each component down to the cube containing this position
+    pub fn floor(&self) -> IntPos {
+        IntPos::new(self.x.floor() as i32, self.y.floor() as i32, self.z.floor() as i32)
+    }
+
+    /// Returns the unit direction vector for the given yaw and pitch.
+    ///
+    /// Angles are in radians. Yaw is measured from the +z axis towards the +x axis,
+    /// and a positive pitch looks upwards along +y.
+    pub fn from_yaw_pitch(yaw: f32, pitch: f32) -> FloatPos {
+        FloatPos::new(yaw.sin() * pitch.cos(), pitch.sin(), yaw.cos() * pitch.cos())
+    }
+    /// Returns the `(yaw, pitch)` pointing along this vector.
+    /// This is the inverse of [from_yaw_pitch](FloatPos::from_yaw_pitch).
+    pub fn to_yaw_pitch(&self) -> (f32, f32) {
+        let horizontal = (self.x * self.x + self.z * self.z).sqrt();
+        (self.x.atan2(self.z), self.y.atan2(horizontal))
+    }
+}
+
+impl Add for FloatPos {
+    type Output = FloatPos;
+    fn add(self, rhs: FloatPos) -> FloatPos { FloatPos::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z) }
+}
+
+impl Sub for FloatPos {
+    type Output = FloatPos;
+    fn sub(self, rhs: FloatPos) -> FloatPos { FloatPos::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z) }
+}
+
+impl Neg for FloatPos {
+    type Output = FloatPos;
+    fn neg(self) -> FloatPos { FloatPos::new(-self.x, -self.y, -self.z) }
+}
+
+impl Mul<f32> for FloatPos {
+    type Output = FloatPos;
+    fn mul(self, rhs: f32) -> FloatPos { FloatPos::new(self.x * rhs, self.y * rhs, self.z * rhs) }
+}
+
+impl AddAssign for FloatPos {
+    fn add_assign(&mut self, rhs: FloatPos) { *self = *self + rhs; }
+}
+
+impl SubAssign for FloatPos {
+    fn sub_assign(&mut self, rhs: FloatPos) { *self = *self - rhs; }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct CubePos {
     pub batch: IntPos,
     pub local_x: u8,
@@ -36,6 +177,28 @@ pub struct CubePos {
     pub local_z: u8,
 }
 
+impl CubePos {
+    /// Splits absolute world coordinates into a batch and local coordinates
+    pub fn from_world(world: IntPos) -> CubePos {
+        CubePos {
+            batch: IntPos::new(world.x.div_euclid(BATCH_SIZE), world.y.div_euclid(BATCH_SIZE), world.z.div_euclid(BATCH_SIZE)),
+            local_x: world.x.rem_euclid(BATCH_SIZE) as u8,
+            local_y: world.y.rem_euclid(BATCH_SIZE) as u8,
+            local_z: world.z.rem_euclid(BATCH_SIZE) as u8,
+        }
+    }
+    /// Returns the absolute world coordinates of this cube.
+    /// Batches beyond `i32::MAX / 16` wrap around the i32 range.
+    pub fn to_world(&self) -> IntPos {
+        self.batch * BATCH_SIZE + self.local()
+    }
+
+    pub fn local(&self) -> IntPos {
+        IntPos::new(self.local_x as i32, self.local_y as i32, self.local_z as i32)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct CubePrecisePos {
     pub cube: CubePos,
     pub face: u8,
@@ -17,11 +17,66 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::io::cube::{FloatPos, IntPos};
+use crate::io::cube::{BATCH_SIZE, FloatPos, IntPos};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct FlexPos {
     pub batch: IntPos,
     pub local: FloatPos,
     pub yaw: f32,
     pub pitch: f32,
 }
+
+impl FlexPos {
+    /// Splits absolute world coordinates into a batch and local coordinates
+    pub fn from_world(world: FloatPos, yaw: f32, pitch: f32) -> FlexPos {
+        let mut ret = FlexPos { batch: IntPos::default(), local: world, yaw, pitch };
+        ret.normalize();
+        ret
+    }
+    /// Returns the absolute world coordinates of this position.
+    /// Precision is lost for batches far from the origin;
+    /// prefer [offset_from](FlexPos::offset_from) when comparing two positions.
+    pub fn to_world(&self) -> FloatPos {
+        self.batch.to_float() * BATCH_SIZE as f32 + self.local
+    }
+
+    /// Moves whole batches out of `local` so that each component is within `0..16`.
+    /// The batch is clamped to the i32 range, leaving the rest of a huge or infinite component in `local`.
+    pub fn normalize(&mut self) {
+        for (batch, local) in [(&mut self.batch.x, &mut self.local.x), (&mut self.batch.y, &mut self.local.y), (&mut self.batch.z, &mut self.local.z)] {
+            // the float-to-int cast saturates, and NaN leaves the batch unchanged
+            let shift = (*local / BATCH_SIZE as f32).floor() as i64;
+            let target = (*batch as i64).saturating_add(shift).clamp(i32::MIN as i64, i32::MAX as i64);
+            *local -= ((target - *batch as i64) * BATCH_SIZE as i64) as f32;
+            *batch = target as i32;
+            // rounding may leave a component at exactly 16.0 if it was slightly negative
+            if *local >= BATCH_SIZE as f32 && *batch < i32::MAX {
+                *batch += 1;
+                *local -= BATCH_SIZE as f32;
+            }
+        }
+    }
+
+    /// Returns the vector from `other` to this position,
+    /// computed batch-relative to preserve precision.
+    pub fn offset_from(&self, other: &FlexPos) -> FloatPos {
+        let offset = |a: i32, b: i32| ((a as i64 - b as i64) * BATCH_SIZE as i64) as f32;
+        let batches = FloatPos::new(offset(self.batch.x, other.batch.x), offset(self.batch.y, other.batch.y), offset(self.batch.z, other.batch.z));
+        batches + (self.local - other.local)
+    }
+    pub fn distance(&self, other: &FlexPos) -> f32 {
+        self.offset_from(other).length()
+    }
+
+    /// Returns the unit vector this position is facing
+    pub fn direction(&self) -> FloatPos {
+        FloatPos::from_yaw_pitch(self.yaw, self.pitch)
+    }
+    /// Sets yaw and pitch to face along `direction`
+    pub fn look_along(&mut self, direction: &FloatPos) {
+        let (yaw, pitch) = direction.to_yaw_pitch();
+        self.yaw = yaw;
+        self.pitch = pitch;
+    }
+}
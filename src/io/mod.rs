@@ -17,13 +17,19 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+#[allow(deprecated, clippy::needless_return)]
 pub mod reader;
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast, clippy::needless_range_loop, clippy::legacy_numeric_constants, clippy::bool_assert_comparison)]
 pub mod reader_test;
 
+#[allow(clippy::manual_is_multiple_of, clippy::needless_return)]
 pub mod writer;
 #[cfg(test)]
+#[allow(clippy::needless_range_loop, clippy::legacy_numeric_constants)]
 pub mod writer_test;
 
 pub mod cube;
 pub mod flex;
+#[cfg(test)]
+pub mod pos_test;
@@ -0,0 +1,261 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use proptest::prelude::*;
+
+use crate::io::cube::{CubePos, CubePrecisePos, FloatPos, IntPos};
+use crate::io::flex::FlexPos;
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+
+pub fn float() -> impl Strategy<Value = f32> {
+    use proptest::num::f32::*;
+    POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO | INFINITE
+}
+
+pub fn int_pos() -> impl Strategy<Value = IntPos> {
+    (any::<i32>(), any::<i32>(), any::<i32>()).prop_map(|(x, y, z)| IntPos { x, y, z })
+}
+
+pub fn float_pos() -> impl Strategy<Value = FloatPos> {
+    (float(), float(), float()).prop_map(|(x, y, z)| FloatPos { x, y, z })
+}
+
+pub fn cube_pos() -> impl Strategy<Value = CubePos> {
+    (int_pos(), 0..16u8, 0..16u8, 0..16u8).prop_map(|(batch, local_x, local_y, local_z)| CubePos { batch, local_x, local_y, local_z })
+}
+
+pub fn cube_precise_pos() -> impl Strategy<Value = CubePrecisePos> {
+    (cube_pos(), 0..16u8, float(), float()).prop_map(|(cube, face, precise_x, precise_y)| CubePrecisePos { cube, face, precise_x, precise_y })
+}
+
+pub fn flex_pos() -> impl Strategy<Value = FlexPos> {
+    (int_pos(), float_pos(), float(), float()).prop_map(|(batch, local, yaw, pitch)| FlexPos { batch, local, yaw, pitch })
+}
+
+macro_rules! round_trip {
+    ($value: expr, $write: ident, $read: ident, $size: expr) => {
+        {
+            let mut writer = CubeWriter::new(Vec::new());
+            writer.$write(&$value).unwrap();
+            writer.write_uint8(0xA5).unwrap();
+            prop_assert_eq!(writer.target.len(), $size + 1);
+
+            let mut reader = CubeReader::new(writer.target.as_slice());
+            prop_assert_eq!(reader.$read().unwrap(), $value);
+            prop_assert_eq!(reader.read_uint8().unwrap(), 0xA5);
+            prop_assert_eq!(reader.remaining(), 0);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn int_pos_round_trip(value in int_pos()) {
+        round_trip!(value, write_int_pos, read_int_pos, 12);
+    }
+
+    #[test]
+    fn float_pos_round_trip(value in float_pos()) {
+        round_trip!(value, write_float_pos, read_float_pos, 12);
+    }
+
+    #[test]
+    fn cube_pos_round_trip(value in cube_pos()) {
+        round_trip!(value, write_cube_pos, read_cube_pos, 14);
+    }
+
+    #[test]
+    fn cube_precise_pos_round_trip(value in cube_precise_pos()) {
+        round_trip!(value, write_cube_precise_pos, read_cube_precise_pos, 22);
+    }
+
+    #[test]
+    fn flex_pos_round_trip(value in flex_pos()) {
+        round_trip!(value, write_flex_pos, read_flex_pos, 32);
+    }
+
+    #[test]
+    fn string_round_trip(value in ".{0,64}") {
+        let mut writer = CubeWriter::new(Vec::new());
+        writer.write_string(&value).unwrap();
+        prop_assert_eq!(writer.target.len(), value.len() + 2);
+        let mut reader = CubeReader::new(writer.target.as_slice());
+        prop_assert_eq!(reader.read_string().unwrap(), value);
+    }
+
+    #[test]
+    fn string32_round_trip(value in ".{0,64}") {
+        let mut writer = CubeWriter::new(Vec::new());
+        writer.write_string32(&value).unwrap();
+        prop_assert_eq!(writer.target.len(), value.len() + 4);
+        let mut reader = CubeReader::new(writer.target.as_slice());
+        prop_assert_eq!(reader.read_string32().unwrap(), value);
+    }
+
+    #[test]
+    fn cube_pos_out_of_range(batch in int_pos(), local_x in 16..=255u8) {
+        let mut writer = CubeWriter::new(Vec::new());
+        let value = CubePos { batch, local_x, local_y: 0, local_z: 0 };
+        prop_assert!(writer.write_cube_pos(&value).is_err());
+    }
+}
+
+#[test]
+fn cube_pos_layout() {
+    let mut writer = CubeWriter::new(Vec::new());
+    writer.write_cube_pos(&CubePos { batch: IntPos { x: 1, y: -1, z: 2 }, local_x: 0xa, local_y: 0xb, local_z: 0xc }).unwrap();
+    assert_eq!(writer.target.as_slice(), &[
+        0x00, 0x00, 0x00, 0x01,
+        0xff, 0xff, 0xff, 0xff,
+        0x00, 0x00, 0x00, 0x02,
+        0xab, 0xc0,
+    ]);
+}
+
+#[test]
+fn cube_precise_pos_layout() {
+    let mut writer = CubeWriter::new(Vec::new());
+    writer.write_cube_precise_pos(&CubePrecisePos {
+        cube: CubePos { batch: IntPos { x: 1, y: -1, z: 2 }, local_x: 0xa, local_y: 0xb, local_z: 0xc },
+        face: 0xd,
+        precise_x: 0.5,
+        precise_y: -2.0,
+    }).unwrap();
+    assert_eq!(writer.target.as_slice(), &[
+        0x00, 0x00, 0x00, 0x01,
+        0xff, 0xff, 0xff, 0xff,
+        0x00, 0x00, 0x00, 0x02,
+        0xab, 0xcd,
+        0x3f, 0x00, 0x00, 0x00,
+        0xc0, 0x00, 0x00, 0x00,
+    ]);
+}
+
+#[test]
+fn cube_pos_world() {
+    let cube = CubePos::from_world(IntPos::new(17, -1, -16));
+    assert_eq!(cube, CubePos { batch: IntPos::new(1, -1, -1), local_x: 1, local_y: 15, local_z: 0 });
+    assert_eq!(cube.to_world(), IntPos::new(17, -1, -16));
+}
+
+#[test]
+fn flex_pos_normalize() {
+    let mut pos = FlexPos { batch: IntPos::new(0, 0, 0), local: FloatPos::new(17.5, -0.5, 16.0), yaw: 0.0, pitch: 0.0 };
+    pos.normalize();
+    assert_eq!(pos.batch, IntPos::new(1, -1, 1));
+    assert_eq!(pos.local, FloatPos::new(1.5, 15.5, 0.0));
+}
+
+#[test]
+fn flex_pos_normalize_tiny_negative() {
+    let mut pos = FlexPos { batch: IntPos::new(0, 0, 0), local: FloatPos::new(-1e-10, 0.0, 0.0), yaw: 0.0, pitch: 0.0 };
+    pos.normalize();
+    assert!(pos.local.x >= 0.0 && pos.local.x < 16.0);
+}
+
+#[test]
+fn flex_pos_distance() {
+    let a = FlexPos::from_world(FloatPos::new(1.0, 2.0, 3.0), 0.0, 0.0);
+    let b = FlexPos::from_world(FloatPos::new(1.0, 2.0, 35.0), 0.0, 0.0);
+    assert_eq!(b.batch, IntPos::new(0, 0, 2));
+    assert_eq!(b.offset_from(&a), FloatPos::new(0.0, 0.0, 32.0));
+    assert_eq!(a.distance(&b), 32.0);
+}
+
+#[test]
+fn int_pos_distance() {
+    let a = IntPos::new(1, 2, 3);
+    let b = IntPos::new(-2, 6, 3);
+    assert_eq!(a.distance_squared(&b), 25);
+    assert_eq!(a.manhattan_distance(&b), 7);
+    assert_eq!(a.chebyshev_distance(&b), 4);
+    assert_eq!(a + b, IntPos::new(-1, 8, 6));
+    assert_eq!(a - b, IntPos::new(3, -4, 0));
+}
+
+#[test]
+fn yaw_pitch_direction() {
+    let close = |a: FloatPos, b: FloatPos| a.distance(&b) < 1e-6;
+    assert!(close(FloatPos::from_yaw_pitch(0.0, 0.0), FloatPos::new(0.0, 0.0, 1.0)));
+    assert!(close(FloatPos::from_yaw_pitch(std::f32::consts::FRAC_PI_2, 0.0), FloatPos::new(1.0, 0.0, 0.0)));
+    assert!(close(FloatPos::from_yaw_pitch(0.0, std::f32::consts::FRAC_PI_2), FloatPos::new(0.0, 1.0, 0.0)));
+}
+
+proptest! {
+    #[test]
+    fn cube_pos_world_round_trip(x in -1_000_000..1_000_000i32, y in -1_000_000..1_000_000i32, z in -1_000_000..1_000_000i32) {
+        let world = IntPos::new(x, y, z);
+        let cube = CubePos::from_world(world);
+        prop_assert!(cube.local_x < 16 && cube.local_y < 16 && cube.local_z < 16);
+        prop_assert_eq!(cube.to_world(), world);
+    }
+
+    #[test]
+    fn flex_pos_normalize_range(x in -1000.0..1000.0f32, y in -1000.0..1000.0f32, z in -1000.0..1000.0f32) {
+        let mut pos = FlexPos { batch: IntPos::new(3, -3, 0), local: FloatPos::new(x, y, z), yaw: 0.0, pitch: 0.0 };
+        let world = pos.to_world();
+        pos.normalize();
+        for local in &[pos.local.x, pos.local.y, pos.local.z] {
+            prop_assert!(*local >= 0.0 && *local < 16.0);
+        }
+        prop_assert!(pos.to_world().distance(&world) < 1e-3);
+    }
+
+    #[test]
+    fn int_pos_wrapping(a in int_pos(), b in int_pos(), factor in any::<i32>()) {
+        prop_assert_eq!(a + b - b, a);
+        prop_assert_eq!(-(-a), a);
+        prop_assert_eq!((a * factor).y, a.y.wrapping_mul(factor));
+    }
+
+    #[test]
+    fn cube_pos_to_world_any_batch(pos in cube_pos()) {
+        // wrapped world coordinates still point at the same cube within its batch
+        prop_assert_eq!(CubePos::from_world(pos.to_world()).local(), pos.local());
+    }
+
+    #[test]
+    fn flex_pos_offset_any_batch(a in int_pos(), b in int_pos(), x in 0.0..16.0f32) {
+        let a = FlexPos { batch: a, local: FloatPos::new(x, 0.0, 0.0), yaw: 0.0, pitch: 0.0 };
+        let b = FlexPos { batch: b, local: FloatPos::default(), yaw: 0.0, pitch: 0.0 };
+        let world = a.to_world();
+        prop_assert!(world.x.is_finite() && world.y.is_finite() && world.z.is_finite());
+        let expected = (a.batch.x as f64 - b.batch.x as f64) * 16.0 + x as f64;
+        prop_assert!((a.offset_from(&b).x as f64 - expected).abs() <= expected.abs() * 1e-6 + 1e-3);
+    }
+
+    #[test]
+    fn flex_pos_normalize_any(batch in int_pos(), x in prop_oneof![float(), Just(f32::NAN)], y in float(), z in float()) {
+        let mut pos = FlexPos { batch, local: FloatPos::new(x, y, z), yaw: 0.0, pitch: 0.0 };
+        pos.normalize();
+        for &(batch, local) in &[(pos.batch.x, pos.local.x), (pos.batch.y, pos.local.y), (pos.batch.z, pos.local.z)] {
+            if local.is_finite() && batch != i32::MIN && batch != i32::MAX {
+                prop_assert!((0.0..16.0).contains(&local), "batch {} local {}", batch, local);
+            }
+        }
+    }
+
+    #[test]
+    fn yaw_pitch_round_trip(yaw in -3.1..3.1f32, pitch in -1.5..1.5f32) {
+        let (yaw2, pitch2) = FloatPos::from_yaw_pitch(yaw, pitch).to_yaw_pitch();
+        prop_assert!((yaw - yaw2).abs() < 1e-4);
+        prop_assert!((pitch - pitch2).abs() < 1e-4);
+    }
+}
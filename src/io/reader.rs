@@ -24,7 +24,7 @@ use byteorder::{BigEndian, ReadBytesExt};
 
 use crate::io::cube::{CubePos, CubePrecisePos, FloatPos, IntPos};
 use crate::io::flex::FlexPos;
-use crate::util::{IoResult, make_io_error, VioResult};
+use crate::util::{io_error, IoResult, make_io_error, VioResult};
 
 pub struct CubeReader<R> {
     current_bit: u8,
@@ -80,19 +80,44 @@ impl<R> CubeReader<R> where R: Read {
         self.current_bit &= 7;
         Result::Ok(ret)
     }
-    /// Reads the next nibble as a boolean.
+    /// Reads the next nibble.
     /// The bit pointer must be at offset `0` or `4`.
     pub fn read_nibble(&mut self) -> IoResult<u8> {
         if self.current_bit & 3 > 0 {
             panic!("Pointer is not at a complete nibble")
         }
-        if self.current_bit == 0 {
-            self.current_byte = read_bytes!(self, 1)[0];
+        Result::Ok(self.read_bits(4)? as u8)
+    }
+    /// Reads the next `n` bits as a big-endian unsigned integer.
+    /// `n` must be within `1..=64`. The bit pointer may be at any offset.
+    pub fn read_bits(&mut self, n: u8) -> IoResult<u64> {
+        if n == 0 || n > 64 {
+            panic!("Bit field width must be within 1..=64")
         }
-        let ret = (self.current_byte >> (4 - self.current_bit)) & 0x0F;
-        self.current_bit ^= 4;
-        self.current_bit &= 7;
-        Result::Ok(ret)
+        let mut value: u64 = 0;
+        let mut left = n;
+        while left > 0 {
+            if self.current_bit == 0 {
+                if left >= 8 {
+                    let size = (left / 8) as usize;
+                    let mut buf = [0; 8];
+                    self.source.read_exact(&mut buf[0..size])?;
+                    for &byte in &buf[0..size] {
+                        value = (value << 8) | byte as u64;
+                    }
+                    left -= size as u8 * 8;
+                    continue;
+                }
+                self.current_byte = read_bytes!(self, 1)[0];
+            }
+            let available = 8 - self.current_bit;
+            let take = available.min(left);
+            let chunk = (self.current_byte >> (available - take)) & (0xFF >> (8 - take));
+            value = (value << take) | chunk as u64;
+            self.current_bit = (self.current_bit + take) & 7;
+            left -= take;
+        }
+        Result::Ok(value)
     }
 
     /// Reads `buf.len()` bytes from the source into `buf`
@@ -102,6 +127,18 @@ impl<R> CubeReader<R> where R: Read {
         Result::Ok(())
     }
 
+    /// Reads `size` bytes from the source into a new vector.
+    /// The vector grows as data is read, so an untrusted size cannot allocate more than the source provides.
+    pub fn read_vec(&mut self, size: usize) -> IoResult<Vec<u8>> {
+        self.ensure_complete_byte();
+        let mut vec = Vec::new();
+        self.source.by_ref().take(size as u64).read_to_end(&mut vec)?;
+        if vec.len() < size {
+            return io_error("Unexpected end of buffer");
+        }
+        Result::Ok(vec)
+    }
+
     /// Reads an i8 from the source
     pub fn read_int8(&mut self) -> IoResult<i8> {
         self.ensure_complete_byte();
@@ -163,12 +200,33 @@ impl<R> CubeReader<R> where R: Read {
         self.source.read_f64::<BigEndian>()
     }
 
+    /// Reads an LEB128-encoded varuint from the source
+    pub fn read_varuint(&mut self) -> IoResult<u64> {
+        self.ensure_complete_byte();
+        let mut value: u64 = 0;
+        for i in 0..10 {
+            let byte = read_bytes!(self, 1)[0];
+            if i == 9 && byte > 1 {
+                break;
+            }
+            value |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Result::Ok(value);
+            }
+        }
+        io_error("varuint is too long")
+    }
+    /// Reads a zigzag-encoded varint from the source
+    pub fn read_varint(&mut self) -> IoResult<i64> {
+        let value = self.read_varuint()?;
+        Result::Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
     /// Reads a string from the source with u16 length prefix
     pub fn read_string(&mut self) -> IoResult<String> {
         self.ensure_complete_byte();
         let size = self.read_uint16()? as usize;
-        let mut vec = vec![0; size];
-        self.source.read_exact(vec.as_mut_slice())?;
+        let vec = self.read_vec(size)?;
         let string = String::from_utf8(vec)
             .map_err(|err| make_io_error(err.description()))?;
         return Result::Ok(string);
@@ -177,8 +235,7 @@ impl<R> CubeReader<R> where R: Read {
     pub fn read_string32(&mut self) -> IoResult<String> {
         self.ensure_complete_byte();
         let size = self.read_uint32()? as usize;
-        let mut vec = vec![0; size];
-        self.source.read_exact(vec.as_mut_slice())?;
+        let vec = self.read_vec(size)?;
         let string = String::from_utf8(vec)
             .map_err(|err| make_io_error(err.description()))?;
         return Result::Ok(string);
@@ -203,31 +260,33 @@ impl<R> CubeReader<R> where R: Read {
 
     /// Reads a [CubePos](CubePos) from the source
     pub fn read_cube_pos(&mut self) -> IoResult<CubePos> {
-        let ret = CubePos {
-            batch: self.read_int_pos()?,
-            local_x: self.read_nibble()?,
-            local_y: self.read_nibble()?,
-            local_z: self.read_nibble()?,
-        };
+        let ret = self.read_cube_pos_fields()?;
         self.read_nop()?;
         Result::Ok(ret)
     }
     /// Reads a [CubePrecisePos](CubePrecisePos) from the source
     pub fn read_cube_precise_pos(&mut self) -> IoResult<CubePrecisePos> {
+        let cube = self.read_cube_pos_fields()?;
+        let face = self.read_nibble()?;
+        // the four nibbles fill exactly two bytes, so this never skips any bits
+        self.read_nop()?;
         Result::Ok(CubePrecisePos {
-            cube: CubePos {
-                batch: self.read_int_pos()?,
-                local_x: self.read_nibble()?,
-                local_y: self.read_nibble()?,
-                local_z: self.read_nibble()?,
-            },
-            face: self.read_nibble()?,
+            cube,
+            face,
             precise_x: self.read_float32()?,
             precise_y: self.read_float32()?,
         })
     }
+    fn read_cube_pos_fields(&mut self) -> IoResult<CubePos> {
+        Result::Ok(CubePos {
+            batch: self.read_int_pos()?,
+            local_x: self.read_nibble()?,
+            local_y: self.read_nibble()?,
+            local_z: self.read_nibble()?,
+        })
+    }
 
-    /// Writes a [FlexPos](FlexPos) to the target
+    /// Reads a [FlexPos](FlexPos) from the source
     pub fn read_flex_pos(&mut self) -> IoResult<FlexPos> {
         Result::Ok(FlexPos {
             batch: self.read_int_pos()?,
@@ -237,3 +296,35 @@ impl<R> CubeReader<R> where R: Read {
         })
     }
 }
+
+/// Implements zero-copy reads for readers backed by an in-memory buffer
+impl<'a> CubeReader<&'a [u8]> {
+    /// Returns the number of unread bytes in the buffer
+    pub fn remaining(&self) -> usize {
+        self.source.len()
+    }
+
+    /// Borrows the next `size` bytes from the buffer without copying
+    pub fn read_slice(&mut self, size: usize) -> IoResult<&'a [u8]> {
+        self.ensure_complete_byte();
+        if self.source.len() < size {
+            return io_error("Unexpected end of buffer");
+        }
+        let (head, tail) = self.source.split_at(size);
+        self.source = tail;
+        Result::Ok(head)
+    }
+
+    /// Borrows a string from the buffer with u16 length prefix
+    pub fn read_str(&mut self) -> IoResult<&'a str> {
+        let size = self.read_uint16()? as usize;
+        let slice = self.read_slice(size)?;
+        std::str::from_utf8(slice).map_err(|err| make_io_error(err.to_string().as_str()))
+    }
+    /// Borrows a string from the buffer with u32 length prefix
+    pub fn read_str32(&mut self) -> IoResult<&'a str> {
+        let size = self.read_uint32()? as usize;
+        let slice = self.read_slice(size)?;
+        std::str::from_utf8(slice).map_err(|err| make_io_error(err.to_string().as_str()))
+    }
+}
@@ -217,3 +217,120 @@ fn read_bit_u8_panic() {
     assert_eq!(reader.read_bit().unwrap(), false);
     reader.read_uint8().unwrap();
 }
+
+#[test]
+fn read_slice() {
+    make_reader!(reader);
+    assert_eq!(reader.read_uint8().unwrap(), 0x12_u8);
+    assert_eq!(reader.read_slice(3).unwrap(), &[0x34, 0x56, 0x78]);
+    assert_eq!(reader.remaining(), 12);
+}
+
+#[test]
+fn read_slice_eof() {
+    make_reader!(reader);
+    assert!(reader.read_slice(17).is_err());
+}
+
+#[test]
+fn read_vec() {
+    make_reader!(reader);
+    assert_eq!(reader.read_vec(3).unwrap(), vec![0x12, 0x34, 0x56]);
+    assert_eq!(reader.read_uint8().unwrap(), 0x78);
+    assert!(reader.read_vec(0xffff_ffff).is_err());
+}
+
+#[test]
+fn read_str() {
+    let buf: &[u8] = &[0x00, 0x05, 0x53, 0x74, 0x65, 0x76, 0x65, 0x00, 0x00, 0x00, 0x02, 0x68, 0x69];
+    let mut reader = CubeReader::new(buf);
+    let steve = reader.read_str().unwrap();
+    let hi = reader.read_str32().unwrap();
+    assert_eq!(steve, "Steve");
+    assert_eq!(hi, "hi");
+    assert_eq!(steve.as_ptr(), buf[2..].as_ptr());
+}
+
+#[test]
+fn read_str_invalid() {
+    let mut reader: CubeReader<&[u8]> = CubeReader::new(&[0x00, 0x01, 0xff]);
+    assert!(reader.read_str().is_err());
+}
+
+#[test]
+fn read_varuint() {
+    let mut reader: CubeReader<&[u8]> = CubeReader::new(&[
+        0x00,
+        0x7f,
+        0x80, 0x01,
+        0xe5, 0x8e, 0x26,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+    ]);
+    assert_eq!(reader.read_varuint().unwrap(), 0);
+    assert_eq!(reader.read_varuint().unwrap(), 0x7f);
+    assert_eq!(reader.read_varuint().unwrap(), 0x80);
+    assert_eq!(reader.read_varuint().unwrap(), 624485);
+    assert_eq!(reader.read_varuint().unwrap(), u64::MAX);
+}
+
+#[test]
+fn read_varuint_overflow() {
+    let mut reader: CubeReader<&[u8]> = CubeReader::new(&[
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02,
+    ]);
+    assert!(reader.read_varuint().is_err());
+}
+
+#[test]
+fn read_varint() {
+    let mut reader: CubeReader<&[u8]> = CubeReader::new(&[
+        0x00,
+        0x01,
+        0x02,
+        0x03,
+        0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+    ]);
+    assert_eq!(reader.read_varint().unwrap(), 0);
+    assert_eq!(reader.read_varint().unwrap(), -1);
+    assert_eq!(reader.read_varint().unwrap(), 1);
+    assert_eq!(reader.read_varint().unwrap(), -2);
+    assert_eq!(reader.read_varint().unwrap(), i64::MAX);
+    assert_eq!(reader.read_varint().unwrap(), i64::MIN);
+}
+
+#[test]
+fn read_bits_unaligned() {
+    make_reader!(reader);
+    assert_eq!(reader.read_bits(3).unwrap(), 0b000);
+    assert_eq!(reader.read_bits(7).unwrap(), 0b1001000);
+    assert_eq!(reader.read_bits(1).unwrap(), 0b1);
+    assert_eq!(reader.read_bits(13).unwrap(), 0b1010001010110);
+    assert_eq!(reader.read_uint8().unwrap(), 0x78);
+}
+
+#[test]
+fn read_bits_wide() {
+    make_reader!(reader);
+    assert_eq!(reader.read_bits(64).unwrap(), 0x1234567890abcdef);
+    assert_eq!(reader.read_bits(4).unwrap(), 0x1);
+    assert_eq!(reader.read_bits(60).unwrap(), 0x234567890abcdef);
+}
+
+#[test]
+fn read_bits_mixed() {
+    make_reader!(reader);
+    assert!(!reader.read_bit().unwrap());
+    assert_eq!(reader.read_bits(3).unwrap(), 0b001);
+    assert_eq!(reader.read_nibble().unwrap(), 0x2);
+    assert_eq!(reader.read_bits(12).unwrap(), 0x345);
+    reader.read_nop().unwrap();
+    assert_eq!(reader.read_uint8().unwrap(), 0x78);
+}
+
+#[test]
+#[should_panic(expected = "Bit field width must be within 1..=64")]
+fn read_bits_too_wide() {
+    make_reader!(reader);
+    reader.read_bits(65).unwrap();
+}
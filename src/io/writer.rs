@@ -57,6 +57,11 @@ impl<W> CubeWriter<W> where W: Write {
         Result::Ok(())
     }
 
+    /// Returns whether the bit pointer is at a complete byte
+    pub fn is_complete_byte(&self) -> bool {
+        self.current_bit == 0
+    }
+
     /// Panics if the current byte is incomplete
     pub fn ensure_complete_byte(&self) {
         if self.current_bit != 0 {
@@ -83,13 +88,39 @@ impl<W> CubeWriter<W> where W: Write {
     /// Only permitted when the bit pointer is at offset `0` or `4`.
     pub fn write_nibble(&mut self, value: u8) -> VioResult {
         if self.current_bit % 4 != 0 { panic!("Pointer is not at a complete nibble") }
-        for i in 0..=3 {
-            self.write_bit(((value >> (3 - i)) & 1) != 0)?;
+        self.write_bits(4, (value & 0x0F) as u64)
+    }
+    /// Writes the lowest `n` bits of `value` to the target, most significant bit first.
+    /// `n` must be within `1..=64`. The bit pointer may be at any offset.
+    pub fn write_bits(&mut self, n: u8, value: u64) -> VioResult {
+        if n == 0 || n > 64 { panic!("Bit field width must be within 1..=64") }
+        if n < 64 && value >> n != 0 { io_error("Value does not fit in the bit field")?; }
+        let mut left = n;
+        while left > 0 {
+            if self.current_bit == 0 && left >= 8 {
+                let size = (left / 8) as usize;
+                let bytes = (value << (64 - left)).to_be_bytes();
+                self.target.write_all(&bytes[0..size])?;
+                left -= size as u8 * 8;
+                continue;
+            }
+            let available = 8 - self.current_bit;
+            let take = available.min(left);
+            let chunk = ((value >> (left - take)) as u8) & (0xFF >> (8 - take));
+            self.current_byte |= chunk << (available - take);
+            self.current_bit += take;
+            left -= take;
+            if self.current_bit >= 8 {
+                let ret = self.target.write_all(&[self.current_byte]);
+                self.current_bit = 0;
+                self.current_byte = 0;
+                ret?;
+            }
         }
         Result::Ok(())
     }
 
-    /// Writes 
+    /// Writes `bytes` to the target
     pub fn write_bytes(&mut self, bytes: &[u8]) -> VioResult {
         self.ensure_complete_byte();
         self.target.write_all(bytes)
@@ -156,6 +187,21 @@ impl<W> CubeWriter<W> where W: Write {
         return self.target.write_f64::<BigEndian>(value);
     }
 
+    /// Writes an LEB128-encoded varuint to the target
+    pub fn write_varuint(&mut self, value: u64) -> VioResult {
+        self.ensure_complete_byte();
+        let mut value = value;
+        while value >= 0x80 {
+            self.target.write_all(&[(value as u8) | 0x80])?;
+            value >>= 7;
+        }
+        self.target.write_all(&[value as u8])
+    }
+    /// Writes a zigzag-encoded varint to the target
+    pub fn write_varint(&mut self, value: i64) -> VioResult {
+        self.write_varuint(((value << 1) ^ (value >> 63)) as u64)
+    }
+
     /// Writes a string to the target with u16 length prefix
     pub fn write_string(&mut self, value: &str) -> VioResult {
         if value.len() > 0xFFFF { io_error("String is too long")?; }
@@ -165,7 +211,7 @@ impl<W> CubeWriter<W> where W: Write {
     /// Writes a string to the target with u32 length prefix
     pub fn write_string32(&mut self, value: &str) -> VioResult {
         if value.len() > 0xFFFFFFFF { io_error("String is too long")?; }
-        self.write_uint16(value.len() as u16)?;
+        self.write_uint32(value.len() as u32)?;
         self.target.write_all(value.as_bytes())
     }
 
@@ -186,24 +232,31 @@ impl<W> CubeWriter<W> where W: Write {
 
     /// Writes a [CubePos](CubePos) to the target
     pub fn write_cube_pos(&mut self, value: &CubePos) -> VioResult {
-        self.write_int_pos(&value.batch)?;
-        self.write_nibble(value.local_x)?;
-        self.write_nibble(value.local_y)?;
-        self.write_nibble(value.local_z)?;
+        self.write_cube_pos_fields(value)?;
         self.write_nop()?;
         Result::Ok(())
     }
     /// Writes a [CubePrecisePos](CubePrecisePos) to the target
     pub fn write_cube_precise_pos(&mut self, value: &CubePrecisePos) -> VioResult {
-        self.write_int_pos(&value.cube.batch)?;
-        self.write_nibble(value.cube.local_x)?;
-        self.write_nibble(value.cube.local_y)?;
-        self.write_nibble(value.cube.local_z)?;
+        if value.face > 0x0F { io_error("Cube face is out of range")?; }
+        self.write_cube_pos_fields(&value.cube)?;
         self.write_nibble(value.face)?;
+        // the four nibbles fill exactly two bytes, so this never writes any padding
+        self.write_nop()?;
         self.write_float32(value.precise_x)?;
         self.write_float32(value.precise_y)?;
         Result::Ok(())
     }
+    fn write_cube_pos_fields(&mut self, value: &CubePos) -> VioResult {
+        if value.local_x > 0x0F || value.local_y > 0x0F || value.local_z > 0x0F {
+            io_error("Cube local coordinates are out of range")?;
+        }
+        self.write_int_pos(&value.batch)?;
+        self.write_nibble(value.local_x)?;
+        self.write_nibble(value.local_y)?;
+        self.write_nibble(value.local_z)?;
+        Result::Ok(())
+    }
 
     /// Writes a [FlexPos](FlexPos) to the target
     pub fn write_flex_pos(&mut self, value: &FlexPos) -> VioResult {
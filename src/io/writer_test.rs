@@ -233,3 +233,86 @@ fn write_bit_u8_panic() {
     cube.write_bit(true).unwrap();
     cube.write_uint8(1).unwrap();
 }
+
+#[test]
+fn write_varuint() {
+    let mut cube = CubeWriter::new(Vec::new());
+    cube.write_varuint(0).unwrap();
+    cube.write_varuint(0x7f).unwrap();
+    cube.write_varuint(0x80).unwrap();
+    cube.write_varuint(624485).unwrap();
+    cube.write_varuint(u64::MAX).unwrap();
+    assert_eq!(cube.target.as_slice(), &[
+        0x00,
+        0x7f,
+        0x80, 0x01,
+        0xe5, 0x8e, 0x26,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+    ]);
+}
+
+#[test]
+fn write_varint() {
+    let mut cube = CubeWriter::new(Vec::new());
+    cube.write_varint(0).unwrap();
+    cube.write_varint(-1).unwrap();
+    cube.write_varint(1).unwrap();
+    cube.write_varint(-2).unwrap();
+    cube.write_varint(i64::MAX).unwrap();
+    cube.write_varint(i64::MIN).unwrap();
+    assert_eq!(cube.target.as_slice(), &[
+        0x00,
+        0x01,
+        0x02,
+        0x03,
+        0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+    ]);
+}
+
+#[test]
+fn write_bits_unaligned() {
+    let mut cube = CubeWriter::new(Vec::new());
+    cube.write_bits(3, 0b000).unwrap();
+    cube.write_bits(7, 0b1001000).unwrap();
+    cube.write_bits(1, 0b1).unwrap();
+    cube.write_bits(13, 0b1010001010110).unwrap();
+    cube.write_uint8(0x78).unwrap();
+    assert_eq!(cube.target.as_slice(), &[0x12, 0x34, 0x56, 0x78]);
+}
+
+#[test]
+fn write_bits_wide() {
+    let mut cube = CubeWriter::new(Vec::new());
+    cube.write_bits(64, 0x1234567890abcdef).unwrap();
+    cube.write_bits(4, 0x1).unwrap();
+    cube.write_bits(60, 0x234567890abcdef).unwrap();
+    assert_eq!(cube.target.as_slice(), &[
+        0x12, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef,
+        0x12, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef,
+    ]);
+}
+
+#[test]
+fn write_bits_mixed() {
+    let mut cube = CubeWriter::new(Vec::new());
+    cube.write_bit(false).unwrap();
+    cube.write_bits(3, 0b001).unwrap();
+    cube.write_nibble(0x2).unwrap();
+    cube.write_bits(12, 0x345).unwrap();
+    cube.write_nop().unwrap();
+    assert_eq!(cube.target.as_slice(), &[0x12, 0x34, 0x50]);
+}
+
+#[test]
+fn write_bits_overflow() {
+    let mut cube = CubeWriter::new(Vec::new());
+    assert!(cube.write_bits(3, 0b1000).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Bit field width must be within 1..=64")]
+fn write_bits_zero() {
+    let mut cube = CubeWriter::new(Vec::new());
+    cube.write_bits(0, 0).unwrap();
+}
@@ -21,8 +21,16 @@
 
 pub mod io;
 pub mod protocol;
+#[allow(clippy::io_other_error)]
 pub mod util;
 
 pub mod cube;
 
 pub mod client;
+pub mod server;
+
+pub mod auth;
+
+pub mod conformance;
+
+pub mod text;
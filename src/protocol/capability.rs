@@ -0,0 +1,107 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! Optional features negotiated at login.
+//!
+//! Each side lists the features it supports in LOGIN_REQUEST and LOGIN_ACCEPT,
+//! and a feature is used only if both sides support it.
+
+use std::fmt;
+
+/// The minor version that appended capabilities to LOGIN_REQUEST and LOGIN_ACCEPT
+pub const CAPABILITIES_MINOR: u32 = 2;
+
+/// The features listed in `[Capabilities]` of `protocol/spec.txt`, which defines none yet.
+/// A variant is added here once its feature is implemented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Capability {}
+
+impl Capability {
+    pub const ALL: &'static [Capability] = &[];
+
+    /// The bit of this capability in the capabilities field
+    pub fn bit(self) -> u32 {
+        match self {}
+    }
+
+    /// The name in `protocol/spec.txt`
+    pub fn name(self) -> &'static str {
+        match self {}
+    }
+}
+
+/// The capabilities field of LOGIN_REQUEST and LOGIN_ACCEPT.
+/// Bits unknown to this library are preserved so that the field is encoded back unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CapabilitySet {
+    bits: u64,
+}
+
+impl CapabilitySet {
+    pub const EMPTY: CapabilitySet = CapabilitySet { bits: 0 };
+
+    pub fn from_bits(bits: u64) -> CapabilitySet {
+        CapabilitySet { bits }
+    }
+
+    pub fn bits(self) -> u64 { self.bits }
+
+    pub fn is_empty(self) -> bool { self.bits == 0 }
+
+    pub fn contains(self, capability: Capability) -> bool {
+        self.bits & (1 << capability.bit()) != 0
+    }
+
+    pub fn insert(&mut self, capability: Capability) {
+        self.bits |= 1 << capability.bit();
+    }
+
+    pub fn with(mut self, capability: Capability) -> CapabilitySet {
+        self.insert(capability);
+        self
+    }
+
+    pub fn remove(&mut self, capability: Capability) {
+        self.bits &= !(1 << capability.bit());
+    }
+
+    /// The capabilities supported by both sides
+    pub fn intersection(self, other: CapabilitySet) -> CapabilitySet {
+        CapabilitySet { bits: self.bits & other.bits }
+    }
+
+    /// The known capabilities in this set
+    pub fn iter(self) -> impl Iterator<Item = Capability> {
+        Capability::ALL.iter().copied().filter(move |&capability| self.contains(capability))
+    }
+}
+
+impl fmt::Display for CapabilitySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator = "";
+        for capability in self.iter() {
+            write!(f, "{}{}", separator, capability.name())?;
+            separator = ", ";
+        }
+        let unknown = Capability::ALL.iter().fold(self.bits, |bits, capability| bits & !(1 << capability.bit()));
+        if unknown != 0 {
+            write!(f, "{}{:#x}", separator, unknown)?;
+        }
+        Result::Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::protocol::capability::{Capability, CapabilitySet};
+
+#[test]
+fn set_operations() {
+    let set = CapabilitySet::EMPTY;
+    assert!(set.is_empty());
+    assert_eq!(set.bits(), 0);
+    assert_eq!(set.iter().count(), 0);
+    assert!(Capability::ALL.is_empty());
+    assert!(!CapabilitySet::from_bits(1).is_empty());
+}
+
+#[test]
+fn intersection() {
+    let client = CapabilitySet::from_bits(0b101);
+    let server = CapabilitySet::from_bits(0b001);
+    assert_eq!(client.intersection(server).bits(), 1);
+    assert!(client.intersection(CapabilitySet::EMPTY).is_empty());
+}
+
+#[test]
+fn unknown_bits() {
+    let set = CapabilitySet::from_bits(0x8000_0000_0000_0005);
+    assert_eq!(set.bits(), 0x8000_0000_0000_0005);
+    assert_eq!(set.iter().count(), 0);
+    assert_eq!(set.to_string(), "0x8000000000000005");
+    assert_eq!(CapabilitySet::EMPTY.to_string(), "");
+}
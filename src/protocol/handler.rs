@@ -17,24 +17,57 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect};
+use crate::protocol::ll::auth::{AuthChallenge, AuthResponse};
+use crate::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect, ServerDisconnectReason};
 use crate::protocol::ll::login_accept::LoginAccept;
 use crate::protocol::ll::login_request::LoginRequest;
 use crate::protocol::ll::ping::{Ping, Pong};
 use crate::protocol::pk::cube_dict::CubeDictSignal;
 use crate::protocol::pk::spawn::SpawnSignal;
-use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::cube_batch::{CubeBatchSignal, CubeBatchSignalRef};
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::version::ProtocolVersion;
+use crate::util::VioResult;
 
 #[allow(unused_variables)]
 pub trait SignalHandler {
-    fn handle_ll_login_request(&mut self, signal: LoginRequest);
-    fn handle_ll_login_accept(&mut self, signal: LoginAccept);
-    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect);
-    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect);
-    fn handle_ll_ping(&mut self, signal: Ping);
-    fn handle_ll_pong(&mut self, signal: Pong);
+    /// The protocol version used to read the signals passed to this handler
+    fn version(&self) -> ProtocolVersion { ProtocolVersion::CURRENT }
 
-    fn handle_pk_spawn(&mut self, signal: SpawnSignal);
-    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal);
-    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal);
+    /// Whether LL_PACKAGE is handled in the current state.
+    /// Rejected packages are not inflated, so peers that have not logged in cannot reach the inflater.
+    fn accepts_packages(&self) -> bool { true }
+
+    fn handle_ll_login_request(&mut self, signal: LoginRequest) -> VioResult;
+    fn handle_ll_auth_challenge(&mut self, signal: AuthChallenge) -> VioResult;
+    fn handle_ll_auth_response(&mut self, signal: AuthResponse) -> VioResult;
+    fn handle_ll_login_accept(&mut self, signal: LoginAccept) -> VioResult;
+    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect) -> VioResult;
+    fn handle_ll_server_disconnect_reason(&mut self, signal: ServerDisconnectReason) -> VioResult;
+    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect) -> VioResult;
+    fn handle_ll_ping(&mut self, signal: Ping) -> VioResult;
+    fn handle_ll_pong(&mut self, signal: Pong) -> VioResult;
+
+    fn handle_pk_spawn(&mut self, signal: SpawnSignal) -> VioResult;
+    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal) -> VioResult;
+    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal) -> VioResult;
+    fn handle_pk_cube_update(&mut self, signal: CubeUpdateSignal) -> VioResult;
+    fn handle_pk_cube_interact(&mut self, signal: CubeInteractSignal) -> VioResult;
+    fn handle_pk_flex_motion(&mut self, signal: FlexMotionSignal) -> VioResult;
+    fn handle_pk_user_motion(&mut self, signal: UserMotionSignal) -> VioResult;
+    fn handle_pk_user_flags(&mut self, signal: UserFlagsSignal) -> VioResult;
+    fn handle_pk_flex_flags(&mut self, signal: FlexFlagsSignal) -> VioResult;
+    fn handle_pk_world_switch(&mut self, signal: WorldSwitchSignal) -> VioResult;
+
+    /// Called instead of `handle_pk_cube_batch` when the package is read from an in-memory buffer.
+    /// Override this to avoid copying the payload.
+    fn handle_pk_cube_batch_ref(&mut self, signal: CubeBatchSignalRef) -> VioResult {
+        self.handle_pk_cube_batch(signal.to_owned())
+    }
 }
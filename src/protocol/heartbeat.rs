@@ -0,0 +1,120 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! Liveness checks and latency measurement with PING and PONG.
+//!
+//! PONG does not identify the PING it answers, but signals arrive in order,
+//! so each PONG answers the oldest unanswered PING.
+//! The round-trip time is smoothed like the TCP retransmission timer of RFC 6298.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::protocol::ll::ping::Ping;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    /// The time between two PINGs
+    pub interval: Duration,
+    /// The number of unanswered PINGs after which the peer is considered dead
+    pub max_missed: usize,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> HeartbeatConfig {
+        HeartbeatConfig { interval: Duration::from_secs(5), max_missed: 3 }
+    }
+}
+
+/// What to do after `Heartbeat::poll`
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeartbeatAction {
+    /// Nothing is due before `Heartbeat::next_ping`
+    Idle,
+    Ping(Ping),
+    /// The peer has not answered `max_missed` PINGs
+    Timeout,
+}
+
+#[derive(Clone, Debug)]
+pub struct Heartbeat {
+    config: HeartbeatConfig,
+    /// The times when the unanswered PINGs were sent, oldest first
+    pending: VecDeque<Instant>,
+    next_ping: Option<Instant>,
+    sent: u64,
+    rtt: Option<Duration>,
+    jitter: Duration,
+}
+
+impl Heartbeat {
+    pub fn new(config: HeartbeatConfig) -> Heartbeat {
+        Heartbeat { config, pending: VecDeque::new(), next_ping: None, sent: 0, rtt: None, jitter: Duration::from_secs(0) }
+    }
+
+    pub fn config(&self) -> &HeartbeatConfig { &self.config }
+
+    /// Sends the first PING on the first poll, then one PING every interval.
+    /// `last_cycle` of each PING is the number of PINGs sent before it.
+    pub fn poll(&mut self, now: Instant) -> HeartbeatAction {
+        if let Some(next_ping) = self.next_ping {
+            if now < next_ping {
+                return HeartbeatAction::Idle;
+            }
+        }
+        if self.pending.len() >= self.config.max_missed {
+            return HeartbeatAction::Timeout;
+        }
+        let ping = Ping { last_cycle: self.sent };
+        self.sent += 1;
+        self.pending.push_back(now);
+        self.next_ping = Some(now + self.config.interval);
+        HeartbeatAction::Ping(ping)
+    }
+
+    /// Matches a PONG with the oldest unanswered PING and returns the round-trip time,
+    /// or `None` if every PING is already answered
+    pub fn on_pong(&mut self, now: Instant) -> Option<Duration> {
+        let sent = self.pending.pop_front()?;
+        let sample = now.saturating_duration_since(sent);
+        match self.rtt {
+            None => {
+                self.rtt = Some(sample);
+                self.jitter = sample / 2;
+            }
+            Some(rtt) => {
+                let deviation = rtt.abs_diff(sample);
+                self.jitter = (self.jitter * 3 + deviation) / 4;
+                self.rtt = Some((rtt * 7 + sample) / 8);
+            }
+        }
+        Some(sample)
+    }
+
+    /// The time when the next PING is due, if any PING has been sent
+    pub fn next_ping(&self) -> Option<Instant> { self.next_ping }
+
+    /// The smoothed round-trip time, once a PONG is received
+    pub fn rtt(&self) -> Option<Duration> { self.rtt }
+
+    /// The smoothed deviation of the round-trip time, once a PONG is received
+    pub fn jitter(&self) -> Option<Duration> { self.rtt.map(|_| self.jitter) }
+
+    /// The number of unanswered PINGs
+    pub fn missed(&self) -> usize { self.pending.len() }
+}
@@ -0,0 +1,85 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::time::{Duration, Instant};
+
+use crate::protocol::heartbeat::{Heartbeat, HeartbeatAction, HeartbeatConfig};
+use crate::protocol::ll::ping::Ping;
+
+fn ms(millis: u64) -> Duration { Duration::from_millis(millis) }
+
+fn heartbeat() -> Heartbeat {
+    Heartbeat::new(HeartbeatConfig { interval: ms(1000), max_missed: 2 })
+}
+
+#[test]
+fn ping_at_interval() {
+    let start = Instant::now();
+    let mut heartbeat = heartbeat();
+    assert_eq!(heartbeat.poll(start), HeartbeatAction::Ping(Ping { last_cycle: 0 }));
+    assert_eq!(heartbeat.next_ping(), Some(start + ms(1000)));
+    assert_eq!(heartbeat.poll(start + ms(999)), HeartbeatAction::Idle);
+    assert_eq!(heartbeat.on_pong(start + ms(100)), Some(ms(100)));
+    assert_eq!(heartbeat.poll(start + ms(1000)), HeartbeatAction::Ping(Ping { last_cycle: 1 }));
+}
+
+#[test]
+fn match_pongs_in_order() {
+    let start = Instant::now();
+    let mut heartbeat = heartbeat();
+    heartbeat.poll(start);
+    heartbeat.poll(start + ms(1000));
+    assert_eq!(heartbeat.missed(), 2);
+    assert_eq!(heartbeat.on_pong(start + ms(1500)), Some(ms(1500)));
+    assert_eq!(heartbeat.on_pong(start + ms(1600)), Some(ms(600)));
+    assert_eq!(heartbeat.on_pong(start + ms(1700)), None);
+    assert_eq!(heartbeat.missed(), 0);
+}
+
+#[test]
+fn smooth_rtt() {
+    let start = Instant::now();
+    let mut heartbeat = heartbeat();
+    assert_eq!(heartbeat.rtt(), None);
+    assert_eq!(heartbeat.jitter(), None);
+
+    heartbeat.poll(start);
+    heartbeat.on_pong(start + ms(80));
+    assert_eq!(heartbeat.rtt(), Some(ms(80)));
+    assert_eq!(heartbeat.jitter(), Some(ms(40)));
+
+    heartbeat.poll(start + ms(1000));
+    heartbeat.on_pong(start + ms(1160));
+    assert_eq!(heartbeat.rtt(), Some(ms(90)));
+    assert_eq!(heartbeat.jitter(), Some(ms(50)));
+}
+
+#[test]
+fn timeout() {
+    let start = Instant::now();
+    let mut heartbeat = heartbeat();
+    heartbeat.poll(start);
+    heartbeat.poll(start + ms(1000));
+    assert_eq!(heartbeat.poll(start + ms(2000)), HeartbeatAction::Timeout);
+
+    let mut heartbeat = self::heartbeat();
+    heartbeat.poll(start);
+    heartbeat.poll(start + ms(1000));
+    heartbeat.on_pong(start + ms(1900));
+    assert_eq!(heartbeat.poll(start + ms(2000)), HeartbeatAction::Ping(Ping { last_cycle: 2 }));
+}
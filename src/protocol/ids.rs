@@ -0,0 +1,36 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+//! Signal IDs generated from `protocol/ll-id.txt` and `protocol/pk-id.txt` by `build.rs`.
+//!
+//! Signals are dispatched by matching `LowLevelId` and `PackedId` exhaustively,
+//! so an ID added to the tables does not compile until it has a decoder.
+
+include!(concat!(env!("OUT_DIR"), "/ids.rs"));
+
+/// Returns the name of a low-level signal ID, e.g. `"LL_PING"`
+pub fn ll_name(id: u8) -> Option<&'static str> {
+    LowLevelId::from_id(id).map(LowLevelId::name)
+}
+
+/// Returns the name of a packed signal ID, e.g. `"PK_GP_FLEX_MOTION"`
+pub fn pk_name(id: u16) -> Option<&'static str> {
+    PackedId::from_id(id).map(PackedId::name)
+}
@@ -0,0 +1,57 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::protocol::ids::*;
+
+#[test]
+fn constants() {
+    assert_eq!(LL_LOGIN_REQUEST, 0x21);
+    assert_eq!(LL_PACKAGE, 0xe1);
+    assert_eq!(PK_LOAD_CUBE_DICT, 0x0101);
+    assert_eq!(PK_GP_FLEX_FLAGS, 0x0314);
+}
+
+#[test]
+fn names() {
+    assert_eq!(ll_name(LL_PING), Some("LL_PING"));
+    assert_eq!(ll_name(0x00), None);
+    assert_eq!(pk_name(PK_GP_FLEX_MOTION), Some("PK_GP_FLEX_MOTION"));
+    assert_eq!(pk_name(0x0000), None);
+}
+
+#[test]
+fn lookup_round_trip() {
+    for &id in LowLevelId::ALL {
+        assert_eq!(LowLevelId::from_id(id.id()), Some(id));
+        assert_eq!(LowLevelId::from_name(id.name()), Some(id));
+    }
+    for &id in PackedId::ALL {
+        assert_eq!(PackedId::from_id(id.id()), Some(id));
+        assert_eq!(PackedId::from_name(id.name()), Some(id));
+    }
+    assert_eq!(LowLevelId::from_name("PK_SPAWN_SPAWN"), None);
+    assert_eq!(PackedId::from_name("LL_PING"), None);
+}
+
+/// Every ID in the tables is listed, so the decoders cover the whole tables
+#[test]
+fn tables_are_complete() {
+    let count = |table: &str| table.lines().skip(1).filter(|line| !line.trim().is_empty()).count();
+    assert_eq!(LowLevelId::ALL.len(), count(include_str!("../../protocol/ll-id.txt")));
+    assert_eq!(PackedId::ALL.len(), count(include_str!("../../protocol/pk-id.txt")));
+}
@@ -0,0 +1,70 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::io::{Read, Write};
+
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::protocol::ll::{read_byte_array, write_byte_array};
+use crate::util::{IoResult, VioResult};
+
+pub use crate::protocol::ids::{LL_AUTH_CHALLENGE, LL_AUTH_RESPONSE};
+
+/// The minor version that introduced AUTH_CHALLENGE and AUTH_RESPONSE
+pub const AUTH_MINOR: u32 = 3;
+
+/// Asks the client to prove that it owns the user ID in LOGIN_REQUEST
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthChallenge {
+    pub nonce: Vec<u8>,
+}
+
+impl AuthChallenge {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint8(LL_AUTH_CHALLENGE)?;
+        write_byte_array(writer, &self.nonce)?;
+        Result::Ok(())
+    }
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Self> {
+        Result::Ok(Self {
+            nonce: read_byte_array(reader)?,
+        })
+    }
+}
+
+/// The answer to AUTH_CHALLENGE, in the format of the authentication scheme of the server
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthResponse {
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl AuthResponse {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint8(LL_AUTH_RESPONSE)?;
+        write_byte_array(writer, &self.public_key)?;
+        write_byte_array(writer, &self.signature)?;
+        Result::Ok(())
+    }
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Self> {
+        Result::Ok(Self {
+            public_key: read_byte_array(reader)?,
+            signature: read_byte_array(reader)?,
+        })
+    }
+}
@@ -17,17 +17,85 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::fmt;
 use std::io::{Read, Write};
 
 use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
 use crate::util::{IoResult, VioResult};
 
-pub const LL_SERVER_DISCONNECT: u8 = 0x61;
+pub use crate::protocol::ids::{LL_SERVER_DISCONNECT, LL_SERVER_DISCONNECT_REASON, LL_CLIENT_DISCONNECT};
 
+/// The minor version that introduced SERVER_DISCONNECT_REASON
+pub const DISCONNECT_REASON_MINOR: u32 = 5;
+
+/// Why the server disconnects the client, see `[DisconnectCode]` in `protocol/spec.txt`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisconnectCode {
+    /// A reason that has no code, described by the message only.
+    /// Codes added in newer minor versions are also read as `Custom`.
+    Custom,
+    /// The client cannot use the protocol version of the server
+    ProtocolMismatch,
+    /// The server or a moderator removed the player
+    Kicked,
+    /// The player is banned from the server
+    Banned,
+    /// The server is stopping or restarting
+    ServerShutdown,
+    /// The client has not answered PING for too long
+    Timeout,
+    /// The client sent a malformed or unexpected signal
+    ProtocolViolation,
+}
+
+impl DisconnectCode {
+    pub const ALL: &'static [DisconnectCode] = &[
+        DisconnectCode::Custom, DisconnectCode::ProtocolMismatch, DisconnectCode::Kicked, DisconnectCode::Banned,
+        DisconnectCode::ServerShutdown, DisconnectCode::Timeout, DisconnectCode::ProtocolViolation,
+    ];
+
+    /// The value of the code field
+    pub fn code(self) -> u8 {
+        match self {
+            DisconnectCode::Custom => 0,
+            DisconnectCode::ProtocolMismatch => 1,
+            DisconnectCode::Kicked => 2,
+            DisconnectCode::Banned => 3,
+            DisconnectCode::ServerShutdown => 4,
+            DisconnectCode::Timeout => 5,
+            DisconnectCode::ProtocolViolation => 6,
+        }
+    }
+
+    pub fn from_code(code: u8) -> DisconnectCode {
+        DisconnectCode::ALL.iter().copied().find(|value| value.code() == code).unwrap_or(DisconnectCode::Custom)
+    }
+
+    /// The name in `protocol/spec.txt`
+    pub fn name(self) -> &'static str {
+        match self {
+            DisconnectCode::Custom => "custom",
+            DisconnectCode::ProtocolMismatch => "protocolMismatch",
+            DisconnectCode::Kicked => "kicked",
+            DisconnectCode::Banned => "banned",
+            DisconnectCode::ServerShutdown => "serverShutdown",
+            DisconnectCode::Timeout => "timeout",
+            DisconnectCode::ProtocolViolation => "protocolViolation",
+        }
+    }
+}
+
+impl fmt::Display for DisconnectCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct ServerDisconnect {
-    reason: String,
-    rejoin: bool,
+    pub reason: String,
+    pub rejoin: bool,
 }
 
 impl ServerDisconnect {
@@ -35,18 +103,49 @@ impl ServerDisconnect {
         writer.write_uint8(LL_SERVER_DISCONNECT)?;
         writer.write_string(self.reason.as_str())?;
         writer.write_bit(self.rejoin)?;
+        writer.write_nop()?;
         Result::Ok(())
     }
     pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Self> {
-        Result::Ok(Self {
+        let ret = Self {
             reason: reader.read_string()?,
-            rejoin: false,
-        })
+            rejoin: reader.read_bit()?,
+        };
+        reader.read_nop()?;
+        Result::Ok(ret)
     }
 }
 
-pub const LL_CLIENT_DISCONNECT: u8 = 0x62;
+/// SERVER_DISCONNECT with a reason code, which replaces SERVER_DISCONNECT for clients that support it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerDisconnectReason {
+    pub code: DisconnectCode,
+    /// The message shown to the player, in the language of LOGIN_REQUEST
+    pub message: String,
+    pub rejoin: bool,
+}
+
+impl ServerDisconnectReason {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint8(LL_SERVER_DISCONNECT_REASON)?;
+        writer.write_uint8(self.code.code())?;
+        writer.write_string(self.message.as_str())?;
+        writer.write_bit(self.rejoin)?;
+        writer.write_nop()?;
+        Result::Ok(())
+    }
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Self> {
+        let ret = Self {
+            code: DisconnectCode::from_code(reader.read_uint8()?),
+            message: reader.read_string()?,
+            rejoin: reader.read_bit()?,
+        };
+        reader.read_nop()?;
+        Result::Ok(ret)
+    }
+}
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct ClientDisconnect {}
 
 impl ClientDisconnect {
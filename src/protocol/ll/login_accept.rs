@@ -21,23 +21,58 @@ use std::io::{Read, Write};
 
 use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
-use crate::util::{IoResult, VioResult};
+use crate::protocol::capability::{CAPABILITIES_MINOR, CapabilitySet};
+use crate::protocol::ll::{read_byte_array, write_byte_array};
+use crate::protocol::ll::login_request::RESUME_MINOR;
+use crate::util::{io_error, IoResult, VioResult};
 
-pub const LL_LOGIN_ACCEPT: u8 = 0x41;
+pub use crate::protocol::ids::LL_LOGIN_ACCEPT;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct LoginAccept {
-    minor_protocol: u32,
+    pub minor_protocol: u32,
+    /// The features supported by the server, only sent since minor protocol 2
+    pub capabilities: CapabilitySet,
+    /// The token to resume this session after a disconnection, empty if the server does not support it.
+    /// Only sent since minor protocol 4, like `resumed`.
+    pub resume_token: Vec<u8>,
+    /// Whether the session of the resume token in LOGIN_REQUEST is resumed
+    pub resumed: bool,
 }
 
 impl LoginAccept {
     pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
         writer.write_uint8(LL_LOGIN_ACCEPT)?;
         writer.write_uint32(self.minor_protocol)?;
+        if self.minor_protocol >= CAPABILITIES_MINOR {
+            writer.write_uint64(self.capabilities.bits())?;
+        } else if !self.capabilities.is_empty() {
+            return io_error("Capabilities cannot be sent before minor protocol 2");
+        }
+        if self.minor_protocol >= RESUME_MINOR {
+            write_byte_array(writer, &self.resume_token)?;
+            writer.write_bit(self.resumed)?;
+            writer.write_nop()?;
+        } else if !self.resume_token.is_empty() || self.resumed {
+            return io_error("Sessions cannot be resumed before minor protocol 4");
+        }
         Result::Ok(())
     }
     pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Self> {
-        Result::Ok(Self {
-            minor_protocol: reader.read_uint32()?,
-        })
+        let minor_protocol = reader.read_uint32()?;
+        let capabilities = if minor_protocol >= CAPABILITIES_MINOR {
+            CapabilitySet::from_bits(reader.read_uint64()?)
+        } else {
+            CapabilitySet::EMPTY
+        };
+        let (resume_token, resumed) = if minor_protocol >= RESUME_MINOR {
+            let token = read_byte_array(reader)?;
+            let resumed = reader.read_bit()?;
+            reader.read_nop()?;
+            (token, resumed)
+        } else {
+            (Vec::new(), false)
+        };
+        Result::Ok(Self { minor_protocol, capabilities, resume_token, resumed })
     }
 }
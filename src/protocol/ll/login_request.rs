@@ -21,17 +21,29 @@ use std::io::{Read, Write};
 
 use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
-use crate::util::{IoResult, VioResult};
+use crate::protocol::capability::{CAPABILITIES_MINOR, CapabilitySet};
+use crate::protocol::ll::{read_byte_array, write_byte_array};
+use crate::protocol::sys_info::SysInfo;
+use crate::util::{io_error, IoResult, VioResult};
 
-pub const LL_LOGIN_REQUEST: u8 = 0x21;
+pub use crate::protocol::ids::LL_LOGIN_REQUEST;
 
+/// The minor version that appended resume tokens to LOGIN_REQUEST and LOGIN_ACCEPT
+pub const RESUME_MINOR: u32 = 4;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct LoginRequest {
-    major_protocol: u32,
-    minor_protocol: u32,
-    username: String,
-    user_id: [u8; 20],
-    language: String,
-    sys_info: String,
+    pub major_protocol: u32,
+    pub minor_protocol: u32,
+    pub username: String,
+    pub user_id: [u8; 20],
+    pub language: String,
+    /// A JSON object, see `parse_sys_info`
+    pub sys_info: String,
+    /// The features supported by the client, only sent since minor protocol 2
+    pub capabilities: CapabilitySet,
+    /// The token of the session to resume, empty for a new session; only sent since minor protocol 4
+    pub resume_token: Vec<u8>,
 }
 
 impl LoginRequest {
@@ -43,12 +55,24 @@ impl LoginRequest {
         writer.write_bytes(&self.user_id)?;
         writer.write_string(self.language.as_str())?;
         writer.write_string(self.sys_info.as_str())?;
+        if self.minor_protocol >= CAPABILITIES_MINOR {
+            writer.write_uint64(self.capabilities.bits())?;
+        } else if !self.capabilities.is_empty() {
+            return io_error("Capabilities cannot be sent before minor protocol 2");
+        }
+        if self.minor_protocol >= RESUME_MINOR {
+            write_byte_array(writer, &self.resume_token)?;
+        } else if !self.resume_token.is_empty() {
+            return io_error("A resume token cannot be sent before minor protocol 4");
+        }
         Result::Ok(())
     }
     pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Self> {
+        let major_protocol = reader.read_uint32()?;
+        let minor_protocol = reader.read_uint32()?;
         Result::Ok(Self {
-            major_protocol: reader.read_uint32()?,
-            minor_protocol: reader.read_uint32()?,
+            major_protocol,
+            minor_protocol,
             username: reader.read_string()?,
             user_id: {
                 let mut array = [0; 20];
@@ -57,6 +81,21 @@ impl LoginRequest {
             },
             language: reader.read_string()?,
             sys_info: reader.read_string()?,
+            capabilities: if minor_protocol >= CAPABILITIES_MINOR {
+                CapabilitySet::from_bits(reader.read_uint64()?)
+            } else {
+                CapabilitySet::EMPTY
+            },
+            resume_token: if minor_protocol >= RESUME_MINOR {
+                read_byte_array(reader)?
+            } else {
+                Vec::new()
+            },
         })
     }
+
+    /// Parses the client details in the sysInfo field
+    pub fn parse_sys_info(&self) -> IoResult<SysInfo> {
+        SysInfo::parse(&self.sys_info)
+    }
 }
@@ -17,18 +17,22 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::io::Read;
+use std::io::{Read, Write};
 
 use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
 use crate::protocol::handler::SignalHandler;
-use crate::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect};
+use crate::protocol::ids::LowLevelId;
+use crate::protocol::ll::auth::{AuthChallenge, AuthResponse};
+use crate::protocol::ll::disconnect::{ClientDisconnect, ServerDisconnect, ServerDisconnectReason};
 use crate::protocol::ll::login_accept::LoginAccept;
 use crate::protocol::ll::login_request::LoginRequest;
-use crate::protocol::ll::package::handle_package;
+use crate::protocol::ll::package::{handle_package, handle_package_slice};
 use crate::protocol::ll::ping::{Ping, Pong};
-use crate::util::{io_error_f, VioResult};
+use crate::util::{io_error, io_error_f, IoResult, VioResult};
 
 pub mod login_request;
+pub mod auth;
 pub mod login_accept;
 pub mod disconnect;
 pub mod ping;
@@ -36,15 +40,59 @@ pub mod package;
 
 pub fn handle_ll<H: SignalHandler, R: Read>(handler: &mut H, reader: &mut CubeReader<R>) -> VioResult {
     let id = reader.read_uint8()?;
-    match id {
-        login_request::LL_LOGIN_REQUEST => handler.handle_ll_login_request(LoginRequest::read(reader)?),
-        login_accept::LL_LOGIN_ACCEPT => handler.handle_ll_login_accept(LoginAccept::read(reader)?),
-        disconnect::LL_SERVER_DISCONNECT => handler.handle_ll_server_disconnect(ServerDisconnect::read(reader)?),
-        disconnect::LL_CLIENT_DISCONNECT => handler.handle_ll_client_disconnect(ClientDisconnect::read(reader)?),
-        ping::LL_PING => handler.handle_ll_ping(Ping::read(reader)?),
-        ping::LL_PONG => handler.handle_ll_pong(Pong::read(reader)?),
-        package::LL_PACKAGE => handle_package(handler, reader)?,
-        _ => io_error_f("Unknown low-level signal ID ".to_owned() + &id.to_string())?,
+    handle_ll_signal(handler, id, reader)
+}
+
+/// Handles an LL from an in-memory buffer without copying the package payload
+pub fn handle_ll_slice<H: SignalHandler>(handler: &mut H, reader: &mut CubeReader<&[u8]>) -> VioResult {
+    let id = reader.read_uint8()?;
+    match low_level_id(handler, id)? {
+        LowLevelId::Package => handle_package_slice(handler, reader),
+        _ => handle_ll_signal(handler, id, reader),
+    }
+}
+
+fn handle_ll_signal<H: SignalHandler, R: Read>(handler: &mut H, id: u8, reader: &mut CubeReader<R>) -> VioResult {
+    // no wildcard arm: every ID in ll-id.txt must have a decoder
+    match low_level_id(handler, id)? {
+        LowLevelId::LoginRequest => handler.handle_ll_login_request(LoginRequest::read(reader)?),
+        LowLevelId::AuthChallenge => handler.handle_ll_auth_challenge(AuthChallenge::read(reader)?),
+        LowLevelId::AuthResponse => handler.handle_ll_auth_response(AuthResponse::read(reader)?),
+        LowLevelId::LoginAccept => handler.handle_ll_login_accept(LoginAccept::read(reader)?),
+        LowLevelId::ServerDisconnect => handler.handle_ll_server_disconnect(ServerDisconnect::read(reader)?),
+        LowLevelId::ClientDisconnect => handler.handle_ll_client_disconnect(ClientDisconnect::read(reader)?),
+        LowLevelId::ServerDisconnectReason => handler.handle_ll_server_disconnect_reason(ServerDisconnectReason::read(reader)?),
+        LowLevelId::Ping => handler.handle_ll_ping(Ping::read(reader)?),
+        LowLevelId::Pong => handler.handle_ll_pong(Pong::read(reader)?),
+        LowLevelId::Package => handle_package(handler, reader),
+    }
+}
+
+/// Resolves an LL ID, rejecting the signals that are not used in the version of the handler
+fn low_level_id<H: SignalHandler>(handler: &H, id: u8) -> IoResult<LowLevelId> {
+    let id = match LowLevelId::from_id(id) {
+        Some(id) => id,
+        None => return io_error_f("Unknown low-level signal ID ".to_owned() + &id.to_string()),
     };
-    Result::Ok(())
+    let version = handler.version();
+    if !version.supports_ll(id) {
+        return io_error_f(format!("{} is not supported in protocol {}", id.name(), version));
+    }
+    if id == LowLevelId::Package && !handler.accepts_packages() {
+        return io_error("Received LL_PACKAGE before LOGIN_ACCEPT");
+    }
+    Result::Ok(id)
+}
+
+/// Writes a `byte[]` field preceded by its uint16 length
+pub(crate) fn write_byte_array<W: Write>(writer: &mut CubeWriter<W>, bytes: &[u8]) -> VioResult {
+    if bytes.len() > 0xFFFF { io_error("Byte array is too long")?; }
+    writer.write_uint16(bytes.len() as u16)?;
+    writer.write_bytes(bytes)
+}
+
+/// Reads a `byte[]` field preceded by its uint16 length
+pub(crate) fn read_byte_array<R: Read>(reader: &mut CubeReader<R>) -> IoResult<Vec<u8>> {
+    let size = reader.read_uint16()? as usize;
+    reader.read_vec(size)
 }
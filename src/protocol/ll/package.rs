@@ -24,17 +24,25 @@ use std::io::{Read, Write};
 use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
 use crate::protocol::handler::SignalHandler;
-use crate::protocol::pk::handle_pk;
-use crate::util::VioResult;
+use crate::protocol::pk::{handle_pk, handle_pk_slice};
+use crate::util::{io_error, IoResult, VioResult};
 
 use self::libflate::deflate::{Decoder, Encoder};
 
-pub const LL_PACKAGE: u8 = 0xe1;
+pub use crate::protocol::ids::LL_PACKAGE;
+
+/// The largest inflated size of a package, which fits a thousand CUBE_BATCH signals.
+/// Larger packages are rejected so that a small frame cannot inflate without bound.
+pub const MAX_PACKAGE_INFLATED: usize = 16 << 20;
 
 pub struct PackageWriter {
     cube: Option<CubeWriter<Encoder<Vec<u8>>>>,
 }
 
+impl Default for PackageWriter {
+    fn default() -> Self { Self::new() }
+}
+
 impl PackageWriter {
     pub fn new() -> Self {
         let encoder = Encoder::new(Vec::new());
@@ -43,6 +51,16 @@ impl PackageWriter {
         }
     }
 
+    /// Appends a PK to the package. `write` must write the ID of the signal, e.g. `|writer| signal.write(writer)`.
+    pub fn write<F>(&mut self, write: F) -> VioResult
+        where F: FnOnce(&mut CubeWriter<Encoder<Vec<u8>>>) -> VioResult {
+        let cube = self.cube.as_mut().expect("the encoder is replaced on flush");
+        cube.write_bit(true)?;
+        cube.write_nop()?;
+        write(cube)
+    }
+
+    /// Writes the package as an LL_PACKAGE signal and starts a new package
     pub fn flush<W: Write>(&mut self, writer: &mut CubeWriter<W>) -> VioResult {
         {
             let mut cube = self.cube.take().unwrap();
@@ -64,9 +82,8 @@ impl PackageWriter {
 
 pub fn handle_package<H: SignalHandler, R: Read>(handler: &mut H, reader: &mut CubeReader<R>) -> VioResult {
     let size = reader.read_uint32()? as usize;
-    let mut buf: Vec<u8> = vec![0; size];
-    reader.read_bytes(buf.as_mut_slice())?;
-    let decoder = Decoder::new(buf.as_slice());
+    let buf = reader.read_vec(size)?;
+    let decoder = Decoder::new(buf.as_slice()).take(MAX_PACKAGE_INFLATED as u64);
     let mut cube = CubeReader::new(decoder);
     while cube.read_bit()? {
         cube.read_nop()?;
@@ -75,3 +92,28 @@ pub fn handle_package<H: SignalHandler, R: Read>(handler: &mut H, reader: &mut C
     cube.read_nop()?;
     Result::Ok(())
 }
+
+/// Handles a package from an in-memory buffer.
+/// The deflated payload is borrowed, and PKs are read from the inflated buffer
+/// so that hot-path signals can be passed to the handler without copying.
+pub fn handle_package_slice<H: SignalHandler>(handler: &mut H, reader: &mut CubeReader<&[u8]>) -> VioResult {
+    let size = reader.read_uint32()? as usize;
+    let inflated = inflate(reader.read_slice(size)?)?;
+    let mut cube = CubeReader::new(inflated.as_slice());
+    while cube.read_bit()? {
+        cube.read_nop()?;
+        handle_pk_slice(handler, &mut cube)?;
+    }
+    cube.read_nop()?;
+    Result::Ok(())
+}
+
+/// Inflates the payload of a package, failing if it exceeds `MAX_PACKAGE_INFLATED`
+pub fn inflate(deflated: &[u8]) -> IoResult<Vec<u8>> {
+    let mut inflated = Vec::<u8>::new();
+    Decoder::new(deflated).take(MAX_PACKAGE_INFLATED as u64 + 1).read_to_end(&mut inflated)?;
+    if inflated.len() > MAX_PACKAGE_INFLATED {
+        return io_error("The package inflates to more than MAX_PACKAGE_INFLATED bytes");
+    }
+    Result::Ok(inflated)
+}
@@ -23,10 +23,11 @@ use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
 use crate::util::{IoResult, VioResult};
 
-pub const LL_PING: u8 = 0x81;
+pub use crate::protocol::ids::{LL_PING, LL_PONG};
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Ping {
-    last_cycle: u64,
+    pub last_cycle: u64,
 }
 
 impl Ping {
@@ -42,8 +43,7 @@ impl Ping {
     }
 }
 
-pub const LL_PONG: u8 = 0x82;
-
+#[derive(Clone, Debug, PartialEq)]
 pub struct Pong {}
 
 impl Pong {
@@ -17,6 +17,53 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::protocol::handler::SignalHandler;
+use crate::protocol::ll::handle_ll_slice;
+use crate::util::{io_error, IoResult, VioResult};
+
 pub mod ll;
 pub mod pk;
 pub mod handler;
+pub mod capability;
+#[cfg(test)]
+mod capability_test;
+pub mod heartbeat;
+#[cfg(test)]
+mod heartbeat_test;
+pub mod ids;
+#[cfg(test)]
+mod ids_test;
+#[cfg(test)]
+pub mod signal_test;
+pub mod sys_info;
+#[cfg(test)]
+mod sys_info_test;
+pub mod version;
+#[cfg(test)]
+mod version_test;
+
+/// The major protocol version implemented by this library
+pub const MAJOR_PROTOCOL: u32 = 1;
+/// The latest minor protocol version implemented by this library
+pub const MINOR_PROTOCOL: u32 = 6;
+
+/// Encodes a websocket binary message using `write`
+pub fn write_frame<F>(write: F) -> IoResult<Vec<u8>>
+    where F: FnOnce(&mut CubeWriter<Vec<u8>>) -> VioResult {
+    let mut writer = CubeWriter::new(Vec::new());
+    write(&mut writer)?;
+    writer.write_nop()?;
+    Result::Ok(writer.target)
+}
+
+/// Decodes a websocket binary message and passes its signals to `handler`
+pub fn handle_frame<H: SignalHandler>(handler: &mut H, frame: &[u8]) -> VioResult {
+    let mut reader = CubeReader::new(frame);
+    handle_ll_slice(handler, &mut reader)?;
+    if reader.remaining() > 0 {
+        io_error("Unexpected trailing bytes after signal")?;
+    }
+    Result::Ok(())
+}
@@ -19,16 +19,19 @@
 
 use std::io::{Read, Write};
 
+use byteorder::{BigEndian, ByteOrder};
+
 use crate::io::cube::IntPos;
 use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
 use crate::util::{IoResult, VioResult};
 
-pub const PK_LOAD_CUBE_BATCH: u16 = 0x0102;
+pub use crate::protocol::ids::PK_LOAD_CUBE_BATCH;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct CubeBatchSignal {
-    pos: IntPos,
-    payload: [u32; 4096],
+    pub pos: IntPos,
+    pub payload: [u32; 4096],
 }
 
 impl CubeBatchSignal {
@@ -52,3 +55,35 @@ impl CubeBatchSignal {
         Result::Ok(pk)
     }
 }
+
+/// A CUBE_BATCH signal borrowing its payload from the package buffer
+pub struct CubeBatchSignalRef<'a> {
+    pos: IntPos,
+    payload: &'a [u8],
+}
+
+impl<'a> CubeBatchSignalRef<'a> {
+    pub fn read(reader: &mut CubeReader<&'a [u8]>) -> IoResult<CubeBatchSignalRef<'a>> {
+        Result::Ok(CubeBatchSignalRef {
+            pos: reader.read_int_pos()?,
+            payload: reader.read_slice(4096 * 4)?,
+        })
+    }
+
+    pub fn pos(&self) -> &IntPos { &self.pos }
+
+    /// Decodes the `index`-th cube ID in the payload
+    pub fn cube(&self, index: usize) -> u32 {
+        BigEndian::read_u32(&self.payload[index * 4..index * 4 + 4])
+    }
+
+    /// Copies the payload into an owned CubeBatchSignal
+    pub fn to_owned(&self) -> CubeBatchSignal {
+        let mut payload = [0; 4096];
+        BigEndian::read_u32_into(self.payload, &mut payload);
+        CubeBatchSignal {
+            pos: self.pos,
+            payload,
+        }
+    }
+}
@@ -24,19 +24,18 @@ use crate::io::writer::CubeWriter;
 use crate::util::{IoResult, VioResult};
 use crate::cube::def::CubeDef;
 
-pub const PK_LOAD_CUBE_DICT: u16 = 0x0101;
+pub use crate::protocol::ids::PK_LOAD_CUBE_DICT;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct CubeDictSignal {
-    size: u32,
-    defs: Vec<Box<CubeDef>>,
+    pub defs: Vec<Box<CubeDef>>,
 }
 
 impl CubeDictSignal {
     pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
         writer.write_uint16(PK_LOAD_CUBE_DICT)?;
-        writer.write_uint32(self.size)?;
-        for i in 0..self.size {
-            let def = &self.defs[i as usize];
+        writer.write_uint32(self.defs.len() as u32)?;
+        for def in &self.defs {
             writer.write_uint32(def.id)?;
             writer.write_string(def.name.as_str())?;
             if !def.name.starts_with("CubePump.") {
@@ -58,6 +57,6 @@ impl CubeDictSignal {
             let def = CubeDef { id, name };
             defs.push(Box::new(def));
         }
-        Result::Ok(CubeDictSignal { size, defs })
+        Result::Ok(CubeDictSignal { defs })
     }
 }
@@ -0,0 +1,51 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use std::io::{Read, Write};
+
+use crate::io::cube::CubePrecisePos;
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::util::{IoResult, VioResult};
+
+pub use crate::protocol::ids::PK_GP_CUBE_INTERACT;
+
+/// Sent when the user clicks on a cube face
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeInteractSignal {
+    pub pos: CubePrecisePos,
+    pub method: u16,
+}
+
+impl CubeInteractSignal {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint16(PK_GP_CUBE_INTERACT)?;
+        writer.write_cube_precise_pos(&self.pos)?;
+        writer.write_uint16(self.method)?;
+        Result::Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<CubeInteractSignal> {
+        Result::Ok(CubeInteractSignal {
+            pos: reader.read_cube_precise_pos()?,
+            method: reader.read_uint16()?,
+        })
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use std::io::{Read, Write};
+
+use crate::io::cube::CubePos;
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::util::{IoResult, VioResult};
+
+pub use crate::protocol::ids::PK_GP_CUBE_UPDATE;
+
+/// Replaces a cube in a loaded batch
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeUpdateSignal {
+    pub pos: CubePos,
+    /// The new cube ID
+    pub cube: u16,
+}
+
+impl CubeUpdateSignal {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint16(PK_GP_CUBE_UPDATE)?;
+        writer.write_cube_pos(&self.pos)?;
+        writer.write_uint16(self.cube)?;
+        Result::Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<CubeUpdateSignal> {
+        Result::Ok(CubeUpdateSignal {
+            pos: reader.read_cube_pos()?,
+            cube: reader.read_uint16()?,
+        })
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::{Read, Write};
+
+use crate::io::cube::FloatPos;
+use crate::io::flex::FlexPos;
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::util::{IoResult, VioResult};
+
+pub use crate::protocol::ids::PK_GP_FLEX_MOTION;
+
+/// FLEX_MOTION only contains fixed-width fields,
+/// so reading it from a package buffer does not allocate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlexMotionSignal {
+    pub event_time: u64,
+    pub pos: FlexPos,
+    pub velocity: FloatPos,
+}
+
+impl FlexMotionSignal {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint16(PK_GP_FLEX_MOTION)?;
+        writer.write_uint64(self.event_time)?;
+        writer.write_flex_pos(&self.pos)?;
+        writer.write_float_pos(&self.velocity)?;
+        Result::Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<FlexMotionSignal> {
+        Result::Ok(FlexMotionSignal {
+            event_time: reader.read_uint64()?,
+            pos: reader.read_flex_pos()?,
+            velocity: reader.read_float_pos()?,
+        })
+    }
+}
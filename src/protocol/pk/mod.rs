@@ -21,22 +21,71 @@ use std::io::Read;
 
 use crate::io::reader::CubeReader;
 use crate::protocol::handler::SignalHandler;
-use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::ids::PackedId;
+use crate::protocol::pk::cube_batch::{CubeBatchSignal, CubeBatchSignalRef};
 use crate::protocol::pk::cube_dict::CubeDictSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
 use crate::protocol::pk::spawn::SpawnSignal;
-use crate::util::{io_error_f, VioResult};
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::util::{io_error_f, IoResult, VioResult};
 
+#[allow(clippy::vec_box)]
 pub mod cube_dict;
 pub mod cube_batch;
 pub mod spawn;
+pub mod cube_update;
+pub mod cube_interact;
+pub mod flex_motion;
+pub mod user_motion;
+pub mod user_flags;
+pub mod flex_flags;
+pub mod world_switch;
 
 pub fn handle_pk<H: SignalHandler, R: Read>(handler: &mut H, reader: &mut CubeReader<R>) -> VioResult {
     let id = reader.read_uint16()?;
-    match id {
-        cube_batch::PK_LOAD_CUBE_BATCH => handler.handle_pk_cube_batch(CubeBatchSignal::read(reader)?),
-        cube_dict::PK_LOAD_CUBE_DICT => handler.handle_pk_cube_dict(CubeDictSignal::read(reader)?),
-        spawn::PK_SPAWN_SPAWN => handler.handle_pk_spawn(SpawnSignal::read(reader)?),
-        _ => io_error_f("Unknown packed signal ID ".to_owned() + &id.to_string())?,
+    handle_pk_signal(handler, id, reader)
+}
+
+/// Handles a PK from an in-memory buffer,
+/// passing borrowed signals to the handler where available.
+pub fn handle_pk_slice<H: SignalHandler>(handler: &mut H, reader: &mut CubeReader<&[u8]>) -> VioResult {
+    let id = reader.read_uint16()?;
+    match packed_id(handler, id)? {
+        PackedId::LoadCubeBatch => handler.handle_pk_cube_batch_ref(CubeBatchSignalRef::read(reader)?),
+        _ => handle_pk_signal(handler, id, reader),
+    }
+}
+
+fn handle_pk_signal<H: SignalHandler, R: Read>(handler: &mut H, id: u16, reader: &mut CubeReader<R>) -> VioResult {
+    // no wildcard arm: every ID in pk-id.txt must have a decoder
+    match packed_id(handler, id)? {
+        PackedId::LoadCubeDict => handler.handle_pk_cube_dict(CubeDictSignal::read(reader)?),
+        PackedId::LoadCubeBatch => handler.handle_pk_cube_batch(CubeBatchSignal::read(reader)?),
+        PackedId::SpawnSpawn => handler.handle_pk_spawn(SpawnSignal::read(reader)?),
+        PackedId::GpCubeUpdate => handler.handle_pk_cube_update(CubeUpdateSignal::read(reader)?),
+        PackedId::GpCubeInteract => handler.handle_pk_cube_interact(CubeInteractSignal::read(reader)?),
+        PackedId::GpFlexMotion => handler.handle_pk_flex_motion(FlexMotionSignal::read(reader)?),
+        PackedId::GpUserMotion => handler.handle_pk_user_motion(UserMotionSignal::read(reader)?),
+        PackedId::GpUserFlags => handler.handle_pk_user_flags(UserFlagsSignal::read(reader)?),
+        PackedId::GpFlexFlags => handler.handle_pk_flex_flags(FlexFlagsSignal::read(reader)?),
+        PackedId::WorldSwitch => handler.handle_pk_world_switch(WorldSwitchSignal::read(reader)?),
+    }
+}
+
+/// Resolves a PK ID, rejecting the signals that are not used in the version of the handler
+fn packed_id<H: SignalHandler>(handler: &H, id: u16) -> IoResult<PackedId> {
+    let id = match PackedId::from_id(id) {
+        Some(id) => id,
+        None => return io_error_f("Unknown packed signal ID ".to_owned() + &id.to_string()),
     };
-    Result::Ok(())
+    let version = handler.version();
+    if !version.supports_pk(id) {
+        return io_error_f(format!("{} is not supported in protocol {}", id.name(), version));
+    }
+    Result::Ok(id)
 }
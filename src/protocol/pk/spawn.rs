@@ -24,10 +24,11 @@ use crate::io::reader::CubeReader;
 use crate::io::writer::CubeWriter;
 use crate::util::{IoResult, VioResult};
 
-pub const PK_SPAWN_SPAWN: u16 = 0x0201;
+pub use crate::protocol::ids::PK_SPAWN_SPAWN;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct SpawnSignal {
-    pos: FlexPos,
+    pub pos: FlexPos,
 }
 
 impl SpawnSignal {
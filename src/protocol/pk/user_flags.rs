@@ -0,0 +1,62 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use std::io::{Read, Write};
+
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::util::{IoResult, VioResult};
+
+pub use crate::protocol::ids::PK_GP_USER_FLAGS;
+
+/// The movement keys held by the user
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserFlagsSignal {
+    pub fly_up: bool,
+    pub fly_down: bool,
+    pub free_fly: bool,
+    pub float: bool,
+    pub crouch: bool,
+}
+
+impl UserFlagsSignal {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint16(PK_GP_USER_FLAGS)?;
+        writer.write_bit(self.fly_up)?;
+        writer.write_bit(self.fly_down)?;
+        writer.write_bit(self.free_fly)?;
+        writer.write_bit(self.float)?;
+        writer.write_bit(self.crouch)?;
+        writer.write_nop()?;
+        Result::Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<UserFlagsSignal> {
+        let ret = UserFlagsSignal {
+            fly_up: reader.read_bit()?,
+            fly_down: reader.read_bit()?,
+            free_fly: reader.read_bit()?,
+            float: reader.read_bit()?,
+            crouch: reader.read_bit()?,
+        };
+        reader.read_nop()?;
+        Result::Ok(ret)
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::{Read, Write};
+
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::util::{IoResult, VioResult};
+
+pub use crate::protocol::ids::PK_WORLD_SWITCH;
+
+/// The minor version that introduced WORLD_SWITCH
+pub const WORLD_SWITCH_MINOR: u32 = 6;
+
+/// Moves a spawned client back to the loading state.
+/// The client drops its loaded batches and cube dictionary, which the server sends again for the new world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldSwitchSignal {
+    /// The name of the new world
+    pub world: String,
+}
+
+impl WorldSwitchSignal {
+    pub fn write<W: Write>(&self, writer: &mut CubeWriter<W>) -> VioResult {
+        writer.write_uint16(PK_WORLD_SWITCH)?;
+        writer.write_string(&self.world)?;
+        Result::Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut CubeReader<R>) -> IoResult<WorldSwitchSignal> {
+        Result::Ok(WorldSwitchSignal {
+            world: reader.read_string()?,
+        })
+    }
+}
@@ -0,0 +1,388 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::cube::def::CubeDef;
+use crate::io::pos_test::{cube_pos, cube_precise_pos, float, flex_pos, float_pos, int_pos};
+use crate::io::reader::CubeReader;
+use crate::io::writer::CubeWriter;
+use crate::protocol::capability::{CAPABILITIES_MINOR, CapabilitySet};
+use crate::protocol::ll::auth::{AuthChallenge, AuthResponse, LL_AUTH_CHALLENGE, LL_AUTH_RESPONSE};
+use crate::protocol::ll::disconnect::{ClientDisconnect, DisconnectCode, LL_CLIENT_DISCONNECT, LL_SERVER_DISCONNECT, LL_SERVER_DISCONNECT_REASON, ServerDisconnect, ServerDisconnectReason};
+use crate::protocol::ll::login_accept::{LL_LOGIN_ACCEPT, LoginAccept};
+use crate::protocol::ll::login_request::{LL_LOGIN_REQUEST, LoginRequest, RESUME_MINOR};
+use crate::protocol::ll::ping::{LL_PING, LL_PONG, Ping, Pong};
+use crate::protocol::pk::cube_batch::{CubeBatchSignal, CubeBatchSignalRef, PK_LOAD_CUBE_BATCH};
+use crate::protocol::pk::cube_dict::{CubeDictSignal, PK_LOAD_CUBE_DICT};
+use crate::protocol::pk::cube_interact::{CubeInteractSignal, PK_GP_CUBE_INTERACT};
+use crate::protocol::pk::cube_update::{CubeUpdateSignal, PK_GP_CUBE_UPDATE};
+use crate::protocol::pk::flex_flags::{FlexFlagsSignal, PK_GP_FLEX_FLAGS};
+use crate::protocol::pk::flex_motion::{FlexMotionSignal, PK_GP_FLEX_MOTION};
+use crate::protocol::pk::spawn::{PK_SPAWN_SPAWN, SpawnSignal};
+use crate::protocol::pk::user_flags::{PK_GP_USER_FLAGS, UserFlagsSignal};
+use crate::protocol::pk::user_motion::{PK_GP_USER_MOTION, UserMotionSignal};
+use crate::protocol::pk::world_switch::{PK_WORLD_SWITCH, WorldSwitchSignal};
+
+fn string() -> impl Strategy<Value = String> {
+    ".{0,32}"
+}
+
+/// Minor versions both before and after the fields added by newer versions
+fn minor_protocol() -> impl Strategy<Value = u32> {
+    prop_oneof![0..CAPABILITIES_MINOR, CAPABILITIES_MINOR..RESUME_MINOR, any::<u32>()]
+}
+
+/// Capabilities are only encoded since `CAPABILITIES_MINOR`
+fn capabilities(minor_protocol: u32, bits: u64) -> CapabilitySet {
+    if minor_protocol >= CAPABILITIES_MINOR { CapabilitySet::from_bits(bits) } else { CapabilitySet::EMPTY }
+}
+
+/// The encoded capabilities field, if the minor version has one
+fn capabilities_bytes(minor_protocol: [u8; 4], bits: [u8; 8]) -> Vec<u8> {
+    if u32::from_be_bytes(minor_protocol) >= CAPABILITIES_MINOR { bits.to_vec() } else { Vec::new() }
+}
+
+/// Resume tokens are only encoded since `RESUME_MINOR`
+fn resume_token(minor_protocol: u32, token: Vec<u8>) -> Vec<u8> {
+    if minor_protocol >= RESUME_MINOR { token } else { Vec::new() }
+}
+
+/// The encoded resume token field, if the minor version has one
+fn resume_token_bytes(minor_protocol: [u8; 4], token: &[u8]) -> Vec<u8> {
+    if u32::from_be_bytes(minor_protocol) >= RESUME_MINOR {
+        [&(token.len() as u16).to_be_bytes()[..], token].concat()
+    } else {
+        Vec::new()
+    }
+}
+
+fn login_request() -> impl Strategy<Value = LoginRequest> {
+    (any::<u32>(), minor_protocol(), string(), any::<[u8; 20]>(), string(), string(), any::<u64>(), vec(any::<u8>(), 0..32))
+        .prop_map(|(major_protocol, minor_protocol, username, user_id, language, sys_info, bits, token)| LoginRequest {
+            major_protocol, minor_protocol, username, user_id, language, sys_info,
+            capabilities: capabilities(minor_protocol, bits),
+            resume_token: resume_token(minor_protocol, token),
+        })
+}
+
+fn server_disconnect() -> impl Strategy<Value = ServerDisconnect> {
+    (string(), any::<bool>()).prop_map(|(reason, rejoin)| ServerDisconnect { reason, rejoin })
+}
+
+fn server_disconnect_reason() -> impl Strategy<Value = ServerDisconnectReason> {
+    (select(DisconnectCode::ALL), string(), any::<bool>())
+        .prop_map(|(code, message, rejoin)| ServerDisconnectReason { code, message, rejoin })
+}
+
+fn cube_def() -> impl Strategy<Value = Box<CubeDef>> {
+    (any::<u32>(), string()).prop_map(|(id, name)| Box::new(CubeDef { id, name }))
+}
+
+fn cube_batch() -> impl Strategy<Value = CubeBatchSignal> {
+    (int_pos(), vec(any::<u32>(), 4096)).prop_map(|(pos, cubes)| {
+        let mut payload = [0; 4096];
+        payload.copy_from_slice(&cubes);
+        CubeBatchSignal { pos, payload }
+    })
+}
+
+fn flex_motion() -> impl Strategy<Value = FlexMotionSignal> {
+    (any::<u64>(), flex_pos(), float_pos())
+        .prop_map(|(event_time, pos, velocity)| FlexMotionSignal { event_time, pos, velocity })
+}
+
+fn user_flags() -> impl Strategy<Value = UserFlagsSignal> {
+    any::<[bool; 5]>().prop_map(|[fly_up, fly_down, free_fly, float, crouch]| UserFlagsSignal {
+        fly_up, fly_down, free_fly, float, crouch,
+    })
+}
+
+/// The wire encoding of a string, built without `CubeWriter`
+fn string_bytes() -> impl Strategy<Value = Vec<u8>> {
+    string().prop_map(|string| {
+        let mut bytes = (string.len() as u16).to_be_bytes().to_vec();
+        bytes.extend_from_slice(string.as_bytes());
+        bytes
+    })
+}
+
+/// Asserts that the signal is read back from its encoding, consuming the whole encoding
+macro_rules! round_trip {
+    ($signal: ty, $value: expr, $read_id: ident, $id: expr) => {
+        {
+            let value = $value;
+            let mut writer = CubeWriter::new(Vec::new());
+            value.write(&mut writer).unwrap();
+            let mut reader = CubeReader::new(writer.target.as_slice());
+            prop_assert_eq!(reader.$read_id().unwrap(), $id);
+            prop_assert_eq!(<$signal>::read(&mut reader).unwrap(), value);
+            prop_assert_eq!(reader.remaining(), 0);
+        }
+    }
+}
+
+/// Asserts that the signal body is read completely and written back to the same bytes
+macro_rules! byte_exact {
+    ($signal: ty, $body: expr, $id: expr) => {
+        {
+            let body: Vec<u8> = $body;
+            let mut reader = CubeReader::new(body.as_slice());
+            let signal = <$signal>::read(&mut reader).unwrap();
+            prop_assert_eq!(reader.remaining(), 0);
+
+            let mut writer = CubeWriter::new(Vec::new());
+            signal.write(&mut writer).unwrap();
+            let mut expected = $id.to_be_bytes().to_vec();
+            expected.extend(body);
+            prop_assert_eq!(writer.target, expected);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn login_request_round_trip(value in login_request()) {
+        round_trip!(LoginRequest, value, read_uint8, LL_LOGIN_REQUEST);
+    }
+
+    #[test]
+    fn auth_challenge_round_trip(nonce in vec(any::<u8>(), 0..64)) {
+        round_trip!(AuthChallenge, AuthChallenge { nonce }, read_uint8, LL_AUTH_CHALLENGE);
+    }
+
+    #[test]
+    fn auth_response_round_trip(public_key in vec(any::<u8>(), 0..64), signature in vec(any::<u8>(), 0..128)) {
+        round_trip!(AuthResponse, AuthResponse { public_key, signature }, read_uint8, LL_AUTH_RESPONSE);
+    }
+
+    #[test]
+    fn login_accept_round_trip(minor_protocol in minor_protocol(), bits in any::<u64>(), token in vec(any::<u8>(), 0..32), resumed in any::<bool>()) {
+        let capabilities = capabilities(minor_protocol, bits);
+        let resume_token = resume_token(minor_protocol, token);
+        let resumed = resumed && minor_protocol >= RESUME_MINOR;
+        round_trip!(LoginAccept, LoginAccept { minor_protocol, capabilities, resume_token, resumed }, read_uint8, LL_LOGIN_ACCEPT);
+    }
+
+    #[test]
+    fn server_disconnect_round_trip(value in server_disconnect()) {
+        round_trip!(ServerDisconnect, value, read_uint8, LL_SERVER_DISCONNECT);
+    }
+
+    #[test]
+    fn server_disconnect_reason_round_trip(value in server_disconnect_reason()) {
+        round_trip!(ServerDisconnectReason, value, read_uint8, LL_SERVER_DISCONNECT_REASON);
+    }
+
+    #[test]
+    fn ping_round_trip(last_cycle in any::<u64>()) {
+        round_trip!(Ping, Ping { last_cycle }, read_uint8, LL_PING);
+    }
+
+    #[test]
+    fn cube_dict_round_trip(defs in vec(cube_def(), 0..8)) {
+        round_trip!(CubeDictSignal, CubeDictSignal { defs }, read_uint16, PK_LOAD_CUBE_DICT);
+    }
+
+    #[test]
+    fn cube_batch_round_trip(value in cube_batch()) {
+        round_trip!(CubeBatchSignal, value, read_uint16, PK_LOAD_CUBE_BATCH);
+    }
+
+    #[test]
+    fn spawn_round_trip(pos in flex_pos()) {
+        round_trip!(SpawnSignal, SpawnSignal { pos }, read_uint16, PK_SPAWN_SPAWN);
+    }
+
+    #[test]
+    fn flex_motion_round_trip(value in flex_motion()) {
+        round_trip!(FlexMotionSignal, value, read_uint16, PK_GP_FLEX_MOTION);
+    }
+
+    #[test]
+    fn cube_update_round_trip(pos in cube_pos(), cube in any::<u16>()) {
+        round_trip!(CubeUpdateSignal, CubeUpdateSignal { pos, cube }, read_uint16, PK_GP_CUBE_UPDATE);
+    }
+
+    #[test]
+    fn cube_interact_round_trip(pos in cube_precise_pos(), method in any::<u16>()) {
+        round_trip!(CubeInteractSignal, CubeInteractSignal { pos, method }, read_uint16, PK_GP_CUBE_INTERACT);
+    }
+
+    #[test]
+    fn user_motion_round_trip(yaw in float(), dash in any::<bool>()) {
+        round_trip!(UserMotionSignal, UserMotionSignal { yaw, dash }, read_uint16, PK_GP_USER_MOTION);
+    }
+
+    #[test]
+    fn user_flags_round_trip(value in user_flags()) {
+        round_trip!(UserFlagsSignal, value, read_uint16, PK_GP_USER_FLAGS);
+    }
+
+    #[test]
+    fn flex_flags_round_trip(crouch in any::<bool>()) {
+        round_trip!(FlexFlagsSignal, FlexFlagsSignal { crouch }, read_uint16, PK_GP_FLEX_FLAGS);
+    }
+
+    #[test]
+    fn world_switch_round_trip(world in string()) {
+        round_trip!(WorldSwitchSignal, WorldSwitchSignal { world }, read_uint16, PK_WORLD_SWITCH);
+    }
+
+    #[test]
+    fn login_request_byte_exact(
+        major in any::<[u8; 4]>(), minor in minor_protocol(), username in string_bytes(),
+        user_id in any::<[u8; 20]>(), language in string_bytes(), sys_info in string_bytes(), bits in any::<[u8; 8]>(),
+        token in vec(any::<u8>(), 0..32),
+    ) {
+        let minor = minor.to_be_bytes();
+        let capabilities = capabilities_bytes(minor, bits);
+        let token = resume_token_bytes(minor, &token);
+        byte_exact!(LoginRequest, [&major[..], &minor, &username, &user_id, &language, &sys_info, &capabilities, &token].concat(), LL_LOGIN_REQUEST);
+    }
+
+    #[test]
+    fn auth_response_byte_exact(public_key in vec(any::<u8>(), 0..64), signature in vec(any::<u8>(), 0..128)) {
+        let body = [
+            (public_key.len() as u16).to_be_bytes().to_vec(), public_key,
+            (signature.len() as u16).to_be_bytes().to_vec(), signature,
+        ].concat();
+        byte_exact!(AuthResponse, body, LL_AUTH_RESPONSE);
+    }
+
+    #[test]
+    fn login_accept_byte_exact(minor in minor_protocol(), bits in any::<[u8; 8]>(), token in vec(any::<u8>(), 0..32), resumed in any::<bool>()) {
+        let minor = minor.to_be_bytes();
+        let mut resume = resume_token_bytes(minor, &token);
+        if !resume.is_empty() {
+            resume.push(if resumed { 0x80 } else { 0 });
+        }
+        byte_exact!(LoginAccept, [minor.to_vec(), capabilities_bytes(minor, bits), resume].concat(), LL_LOGIN_ACCEPT);
+    }
+
+    /// The padding after `rejoin` is zero in canonical encodings
+    #[test]
+    fn server_disconnect_byte_exact(reason in string_bytes(), rejoin in any::<bool>()) {
+        byte_exact!(ServerDisconnect, [reason, vec![(rejoin as u8) << 7]].concat(), LL_SERVER_DISCONNECT);
+    }
+
+    #[test]
+    fn server_disconnect_reason_byte_exact(code in select(DisconnectCode::ALL), message in string_bytes(), rejoin in any::<bool>()) {
+        byte_exact!(ServerDisconnectReason, [vec![code.code()], message, vec![(rejoin as u8) << 7]].concat(), LL_SERVER_DISCONNECT_REASON);
+    }
+
+    #[test]
+    fn ping_byte_exact(body in any::<[u8; 8]>()) {
+        byte_exact!(Ping, body.to_vec(), LL_PING);
+    }
+
+    #[test]
+    fn cube_dict_byte_exact(defs in vec((any::<[u8; 4]>(), string_bytes()), 0..8)) {
+        let mut body = (defs.len() as u32).to_be_bytes().to_vec();
+        for (id, name) in defs {
+            body.extend_from_slice(&id);
+            body.extend(name);
+        }
+        byte_exact!(CubeDictSignal, body, PK_LOAD_CUBE_DICT);
+    }
+
+    #[test]
+    fn cube_batch_byte_exact(body in vec(any::<u8>(), 12 + 4096 * 4)) {
+        byte_exact!(CubeBatchSignal, body, PK_LOAD_CUBE_BATCH);
+    }
+
+    /// Any bit pattern, including NaN payloads, is preserved in float fields
+    #[test]
+    fn spawn_byte_exact(body in vec(any::<u8>(), 32)) {
+        byte_exact!(SpawnSignal, body, PK_SPAWN_SPAWN);
+    }
+
+    #[test]
+    fn flex_motion_byte_exact(body in vec(any::<u8>(), 52)) {
+        byte_exact!(FlexMotionSignal, body, PK_GP_FLEX_MOTION);
+    }
+
+    /// The padding after the three local nibbles is zero in canonical encodings
+    #[test]
+    fn cube_update_byte_exact(batch in any::<[u8; 12]>(), local in any::<u16>(), cube in any::<[u8; 2]>()) {
+        byte_exact!(CubeUpdateSignal, [&batch[..], &(local & 0xfff0).to_be_bytes(), &cube].concat(), PK_GP_CUBE_UPDATE);
+    }
+
+    #[test]
+    fn cube_interact_byte_exact(body in vec(any::<u8>(), 24)) {
+        byte_exact!(CubeInteractSignal, body, PK_GP_CUBE_INTERACT);
+    }
+
+    #[test]
+    fn user_motion_byte_exact(yaw in any::<[u8; 4]>(), dash in any::<bool>()) {
+        byte_exact!(UserMotionSignal, [&yaw[..], &[(dash as u8) << 7]].concat(), PK_GP_USER_MOTION);
+    }
+
+    #[test]
+    fn user_flags_byte_exact(flags in any::<u8>()) {
+        byte_exact!(UserFlagsSignal, vec![flags & 0xf8], PK_GP_USER_FLAGS);
+    }
+
+    #[test]
+    fn flex_flags_byte_exact(crouch in any::<bool>()) {
+        byte_exact!(FlexFlagsSignal, vec![(crouch as u8) << 7], PK_GP_FLEX_FLAGS);
+    }
+
+    #[test]
+    fn world_switch_byte_exact(world in string_bytes()) {
+        byte_exact!(WorldSwitchSignal, world, PK_WORLD_SWITCH);
+    }
+
+    #[test]
+    fn cube_batch_ref(body in vec(any::<u8>(), 12 + 4096 * 4)) {
+        let borrowed = CubeBatchSignalRef::read(&mut CubeReader::new(body.as_slice())).unwrap();
+        let owned = CubeBatchSignal::read(&mut CubeReader::new(body.as_slice())).unwrap();
+        prop_assert_eq!(borrowed.pos(), &owned.pos);
+        for index in 0..4096 {
+            prop_assert_eq!(borrowed.cube(index), owned.payload[index]);
+        }
+        prop_assert_eq!(borrowed.to_owned(), owned);
+    }
+}
+
+#[test]
+fn empty_signals() {
+    let mut writer = CubeWriter::new(Vec::new());
+    ClientDisconnect {}.write(&mut writer).unwrap();
+    Pong {}.write(&mut writer).unwrap();
+    assert_eq!(writer.target, vec![LL_CLIENT_DISCONNECT, LL_PONG]);
+
+    let mut reader = CubeReader::new(&[][..]);
+    assert_eq!(ClientDisconnect::read(&mut reader).unwrap(), ClientDisconnect {});
+    assert_eq!(Pong::read(&mut reader).unwrap(), Pong {});
+}
+
+/// Codes added by newer minor versions are read as `Custom`
+#[test]
+fn unknown_disconnect_code() {
+    for &code in DisconnectCode::ALL {
+        assert_eq!(DisconnectCode::from_code(code.code()), code);
+    }
+    let body = [&[0xff][..], &[0, 2], b"hi", &[0x80]].concat();
+    let signal = ServerDisconnectReason::read(&mut CubeReader::new(body.as_slice())).unwrap();
+    assert_eq!(signal, ServerDisconnectReason { code: DisconnectCode::Custom, message: "hi".to_owned(), rejoin: true });
+}
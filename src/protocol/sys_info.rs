@@ -0,0 +1,68 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! The client details in the sysInfo field of LOGIN_REQUEST.
+//!
+//! sysInfo is a JSON object. The keys known to this library are parsed into `SysInfo`,
+//! and the other keys are kept in `SysInfo::extra`, so that they survive a round trip.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::util::{io_error_f, IoResult};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SysInfo {
+    /// The name of the client implementation, e.g. a browser client or a bot framework
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_version: Option<String>,
+    /// The operating system or browser of the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screen_size: Option<ScreenSize>,
+    /// The number of batches rendered in each direction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_distance: Option<u32>,
+    /// The keys not listed above
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// The size of the game viewport in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SysInfo {
+    pub fn parse(json: &str) -> IoResult<SysInfo> {
+        serde_json::from_str(json).or_else(|err| io_error_f(format!("Malformed sysInfo: {}", err)))
+    }
+
+    /// Encodes the value of the sysInfo field
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("SysInfo only contains JSON values")
+    }
+}
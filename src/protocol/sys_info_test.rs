@@ -0,0 +1,59 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use serde_json::json;
+
+use crate::protocol::sys_info::{ScreenSize, SysInfo};
+
+#[test]
+fn parse_empty() {
+    assert_eq!(SysInfo::parse("{}").unwrap(), SysInfo::default());
+    assert_eq!(SysInfo::default().to_json(), "{}");
+}
+
+#[test]
+fn parse_known_keys() {
+    let info = SysInfo::parse(r#"{
+        "clientName": "cube-web", "clientVersion": "0.3.1", "platform": "Firefox",
+        "screenSize": {"width": 1920, "height": 1080}, "renderDistance": 8
+    }"#).unwrap();
+    assert_eq!(info.client_name.as_deref(), Some("cube-web"));
+    assert_eq!(info.client_version.as_deref(), Some("0.3.1"));
+    assert_eq!(info.platform.as_deref(), Some("Firefox"));
+    assert_eq!(info.screen_size, Some(ScreenSize { width: 1920, height: 1080 }));
+    assert_eq!(info.render_distance, Some(8));
+    assert!(info.extra.is_empty());
+}
+
+#[test]
+fn unknown_keys_round_trip() {
+    let json = r#"{"clientName":"bot","gpu":{"vendor":"none"},"modded":true}"#;
+    let info = SysInfo::parse(json).unwrap();
+    assert_eq!(info.extra.get("gpu"), Some(&json!({"vendor": "none"})));
+    assert_eq!(info.extra.get("modded"), Some(&json!(true)));
+    assert_eq!(info.to_json(), json);
+    assert_eq!(SysInfo::parse(&info.to_json()).unwrap(), info);
+}
+
+#[test]
+fn parse_malformed() {
+    for json in &["", "[]", "\"{}\"", r#"{"renderDistance": "far"}"#, r#"{"screenSize": {"width": 800}}"#] {
+        let err = SysInfo::parse(json).unwrap_err();
+        assert!(err.to_string().starts_with("Malformed sysInfo: "), "{}", err);
+    }
+}
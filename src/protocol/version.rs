@@ -0,0 +1,151 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! Protocol versions and their negotiation.
+//!
+//! Each connection uses the version negotiated during login.
+//! Signal types introduced in a newer minor version are rejected by the dispatcher
+//! if the version returned by `SignalHandler::version` is older.
+//!
+//! Codecs do not take the version. LOGIN_REQUEST and LOGIN_ACCEPT gate their newer fields
+//! on the `minor_protocol` they carry, since they are exchanged before the version is negotiated,
+//! and every other signal has a single format, so a changed format needs a new signal ID.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::protocol::{MAJOR_PROTOCOL, MINOR_PROTOCOL};
+use crate::protocol::ids::{LowLevelId, PackedId};
+use crate::protocol::ll::auth::AUTH_MINOR;
+use crate::protocol::ll::disconnect::DISCONNECT_REASON_MINOR;
+use crate::protocol::pk::world_switch::WORLD_SWITCH_MINOR;
+use crate::text::Text;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    /// The latest version implemented by this library
+    pub const CURRENT: ProtocolVersion = ProtocolVersion { major: MAJOR_PROTOCOL, minor: MINOR_PROTOCOL };
+
+    pub fn new(major: u32, minor: u32) -> ProtocolVersion {
+        ProtocolVersion { major, minor }
+    }
+
+    /// Negotiates the version of a connection.
+    /// The client must implement the major version of the server and at least its minor version,
+    /// and the connection uses the version of the server.
+    pub fn negotiate(client: ProtocolVersion, server: ProtocolVersion) -> Result<ProtocolVersion, VersionRejection> {
+        if client.major != server.major {
+            return Result::Err(VersionRejection::MajorMismatch { client, server });
+        }
+        if client.minor < server.minor {
+            return Result::Err(VersionRejection::ClientOutdated { client, server });
+        }
+        Result::Ok(server)
+    }
+
+    /// Whether the signals and fields introduced in `minor` are used in this version
+    pub fn has_minor(self, minor: u32) -> bool {
+        self.minor >= minor
+    }
+
+    pub fn supports_ll(self, id: LowLevelId) -> bool {
+        self.has_minor(id.since_minor())
+    }
+
+    pub fn supports_pk(self, id: PackedId) -> bool {
+        self.has_minor(id.since_minor())
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl LowLevelId {
+    /// The minor version that introduced this signal
+    pub fn since_minor(self) -> u32 {
+        match self {
+            LowLevelId::LoginRequest | LowLevelId::LoginAccept
+            | LowLevelId::ServerDisconnect | LowLevelId::ClientDisconnect
+            | LowLevelId::Ping | LowLevelId::Pong | LowLevelId::Package => 0,
+            LowLevelId::AuthChallenge | LowLevelId::AuthResponse => AUTH_MINOR,
+            LowLevelId::ServerDisconnectReason => DISCONNECT_REASON_MINOR,
+        }
+    }
+}
+
+impl PackedId {
+    /// The minor version that introduced this signal
+    pub fn since_minor(self) -> u32 {
+        match self {
+            PackedId::LoadCubeDict | PackedId::LoadCubeBatch | PackedId::SpawnSpawn
+            | PackedId::GpCubeUpdate | PackedId::GpCubeInteract | PackedId::GpFlexMotion
+            | PackedId::GpUserMotion | PackedId::GpUserFlags | PackedId::GpFlexFlags => 0,
+            PackedId::WorldSwitch => WORLD_SWITCH_MINOR,
+        }
+    }
+}
+
+/// The reason why a client and a server cannot talk to each other
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionRejection {
+    /// The client and the server implement different major versions
+    MajorMismatch { client: ProtocolVersion, server: ProtocolVersion },
+    /// The client implements an older minor version than the server
+    ClientOutdated { client: ProtocolVersion, server: ProtocolVersion },
+}
+
+impl VersionRejection {
+    pub fn client(&self) -> ProtocolVersion {
+        match *self {
+            VersionRejection::MajorMismatch { client, .. } | VersionRejection::ClientOutdated { client, .. } => client,
+        }
+    }
+
+    pub fn server(&self) -> ProtocolVersion {
+        match *self {
+            VersionRejection::MajorMismatch { server, .. } | VersionRejection::ClientOutdated { server, .. } => server,
+        }
+    }
+}
+
+impl VersionRejection {
+    /// The message shown to the player, see `server::catalog` for the keys
+    pub fn text(&self) -> Text {
+        let (key, fallback) = match self {
+            VersionRejection::MajorMismatch { .. } => ("disconnect.incompatibleProtocol", "Incompatible protocol {0}, the server uses {1}"),
+            VersionRejection::ClientOutdated { .. } => ("disconnect.outdatedProtocol", "Outdated protocol {0}, the server uses {1}"),
+        };
+        Text::translatable(key, vec![self.client().to_string(), self.server().to_string()], fallback)
+    }
+}
+
+impl fmt::Display for VersionRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl Error for VersionRejection {}
@@ -0,0 +1,55 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::protocol::ids::{LowLevelId, PackedId};
+use crate::protocol::version::{ProtocolVersion, VersionRejection};
+
+#[test]
+fn negotiate() {
+    let server = ProtocolVersion::new(1, 2);
+    assert_eq!(ProtocolVersion::negotiate(ProtocolVersion::new(1, 2), server), Ok(server));
+    assert_eq!(ProtocolVersion::negotiate(ProtocolVersion::new(1, 5), server), Ok(server));
+
+    let client = ProtocolVersion::new(1, 1);
+    assert_eq!(ProtocolVersion::negotiate(client, server), Err(VersionRejection::ClientOutdated { client, server }));
+    let client = ProtocolVersion::new(2, 2);
+    assert_eq!(ProtocolVersion::negotiate(client, server), Err(VersionRejection::MajorMismatch { client, server }));
+    let client = ProtocolVersion::new(0, 9);
+    assert_eq!(ProtocolVersion::negotiate(client, server), Err(VersionRejection::MajorMismatch { client, server }));
+}
+
+#[test]
+fn rejection_message() {
+    let rejection = VersionRejection::ClientOutdated { client: ProtocolVersion::new(1, 0), server: ProtocolVersion::new(1, 1) };
+    assert_eq!(rejection.to_string(), "Outdated protocol 1.0, the server uses 1.1");
+    assert_eq!(rejection.client(), ProtocolVersion::new(1, 0));
+    assert_eq!(rejection.server(), ProtocolVersion::new(1, 1));
+}
+
+#[test]
+fn current_version_supports_every_signal() {
+    assert!(LowLevelId::ALL.iter().all(|&id| ProtocolVersion::CURRENT.supports_ll(id)));
+    assert!(PackedId::ALL.iter().all(|&id| ProtocolVersion::CURRENT.supports_pk(id)));
+}
+
+#[test]
+fn world_switch_needs_minor_6() {
+    assert!(!ProtocolVersion::new(1, 5).supports_pk(PackedId::WorldSwitch));
+    assert!(ProtocolVersion::new(1, 6).supports_pk(PackedId::WorldSwitch));
+    assert!(ProtocolVersion::new(1, 5).supports_pk(PackedId::SpawnSpawn));
+}
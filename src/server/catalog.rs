@@ -0,0 +1,94 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Translates texts into the language of each session.
+//!
+//! A catalog holds the templates of each language by translation key. A text is looked up in
+//! the language of LOGIN_REQUEST (e.g. `en_US`), then in its base language (`en`), then in the
+//! default language of the catalog, and the fallback of the text is used if none has the key.
+//!
+//! The texts sent by this library use these keys:
+//!
+//! - `disconnect.timeout`
+//! - `disconnect.incompatibleProtocol` and `disconnect.outdatedProtocol`,
+//!   with the versions of the client and the server as arguments
+//! - `auth.malformed`, `auth.keyMismatch` and `auth.badSignature`
+//! - `login.notAllowed` and `login.serverFull`
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::text::Text;
+use crate::util::{io_error_f, VioResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    default_language: String,
+    /// The templates by language and key
+    languages: HashMap<String, HashMap<String, String>>,
+}
+
+impl Catalog {
+    /// `default_language` is used for the keys that the language of a session does not translate
+    pub fn new(default_language: &str) -> Catalog {
+        Catalog { default_language: default_language.to_owned(), languages: HashMap::new() }
+    }
+
+    pub fn default_language(&self) -> &str { &self.default_language }
+
+    pub fn insert(&mut self, language: &str, key: &str, template: &str) {
+        self.languages.entry(language.to_owned()).or_default().insert(key.to_owned(), template.to_owned());
+    }
+
+    /// Adds the templates of a JSON object that maps keys to templates
+    pub fn insert_json(&mut self, language: &str, json: &str) -> VioResult {
+        let object = match serde_json::from_str::<Value>(json) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => return io_error_f(format!("Malformed translations of {}: expected an object", language)),
+            Err(err) => return io_error_f(format!("Malformed translations of {}: {}", language, err)),
+        };
+        for (key, template) in object {
+            match template {
+                Value::String(template) => self.insert(language, &key, &template),
+                _ => return io_error_f(format!("Malformed translations of {}: {} is not a string", language, key)),
+            }
+        }
+        Result::Ok(())
+    }
+
+    /// The template of `key` in `language`, its base language or the default language
+    pub fn lookup(&self, language: &str, key: &str) -> Option<&str> {
+        [language, base_language(language), &self.default_language, base_language(&self.default_language)].iter()
+            .filter_map(|language| self.languages.get(*language))
+            .find_map(|templates| templates.get(key))
+            .map(String::as_str)
+    }
+
+    /// Formats `text` in `language`
+    pub fn resolve(&self, text: &Text, language: &str) -> String {
+        let template = if text.is_literal() { None } else { self.lookup(language, &text.key) };
+        text.format(template.unwrap_or(&text.fallback))
+    }
+}
+
+/// `en_US` => `en`
+fn base_language(language: &str) -> &str {
+    language.split(['_', '-']).next().unwrap_or(language)
+}
@@ -0,0 +1,60 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::server::catalog::Catalog;
+use crate::text::Text;
+
+fn catalog() -> Catalog {
+    let mut catalog = Catalog::new("en");
+    catalog.insert("en", "login.serverFull", "The server is full");
+    catalog.insert("en", "greeting", "Hello, {0}");
+    catalog.insert("de", "login.serverFull", "Der Server ist voll");
+    catalog.insert("de_AT", "greeting", "Servus, {0}");
+    catalog
+}
+
+#[test]
+fn resolve_by_language() {
+    let catalog = catalog();
+    let full = Text::translatable("login.serverFull", Vec::new(), "Full");
+    let greeting = Text::translatable("greeting", vec!["Steve".to_owned()], "Hi {0}");
+    assert_eq!(catalog.resolve(&full, "de_DE"), "Der Server ist voll");
+    assert_eq!(catalog.resolve(&full, "fr_FR"), "The server is full");
+    assert_eq!(catalog.resolve(&greeting, "de_AT"), "Servus, Steve");
+    assert_eq!(catalog.resolve(&greeting, "de_DE"), "Hello, Steve");
+}
+
+#[test]
+fn fallback() {
+    let catalog = catalog();
+    let unknown = Text::translatable("unknown", vec!["x".to_owned()], "Unknown {0}");
+    assert_eq!(catalog.resolve(&unknown, "en_US"), "Unknown x");
+    assert_eq!(catalog.resolve(&Text::literal("greeting"), "en"), "greeting");
+    assert_eq!(Catalog::new("en").resolve(&unknown, "de"), "Unknown x");
+}
+
+#[test]
+fn insert_json() {
+    let mut catalog = Catalog::new("en");
+    catalog.insert_json("fr", r#"{"greeting": "Bonjour, {0}"}"#).unwrap();
+    assert_eq!(catalog.lookup("fr_CA", "greeting"), Some("Bonjour, {0}"));
+    assert!(catalog.insert_json("fr", "[]").is_err());
+    assert!(catalog.insert_json("fr", r#"{"greeting": 1}"#).is_err());
+    assert!(catalog.insert_json("fr", "{").is_err());
+}
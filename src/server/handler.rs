@@ -0,0 +1,169 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::protocol::handler::SignalHandler;
+use crate::protocol::ll::auth::{AUTH_MINOR, AuthChallenge, AuthResponse};
+use crate::protocol::ll::disconnect::{ClientDisconnect, DisconnectCode, ServerDisconnect, ServerDisconnectReason};
+use crate::protocol::ll::login_accept::LoginAccept;
+use crate::protocol::ll::login_request::LoginRequest;
+use crate::protocol::ll::ping::{Ping, Pong};
+use crate::protocol::pk::spawn::SpawnSignal;
+use crate::protocol::pk::cube_dict::CubeDictSignal;
+use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::flex_motion::FlexMotionSignal;
+use crate::protocol::pk::cube_update::CubeUpdateSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::flex_flags::FlexFlagsSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::server::{Session, SessionAdapter, SessionState};
+use crate::protocol::version::ProtocolVersion;
+use crate::util::{io_error, VioResult};
+
+macro_rules! sc_only {
+    () => (
+        io_error("Received a server-to-client signal from the client")
+    )
+}
+
+#[allow(unused_variables)]
+impl<A: SessionAdapter> SignalHandler for Session<A> {
+    fn version(&self) -> ProtocolVersion {
+        self.version.unwrap_or(self.config.version)
+    }
+
+    fn accepts_packages(&self) -> bool {
+        self.state == SessionState::Loading || self.state == SessionState::Spawned
+    }
+
+    fn handle_ll_login_request(&mut self, signal: LoginRequest) -> VioResult {
+        if self.state != SessionState::Initial {
+            return io_error("Received duplicate LOGIN_REQUEST");
+        }
+        self.sys_info = signal.parse_sys_info().ok();
+        let client = ProtocolVersion::new(signal.major_protocol, signal.minor_protocol);
+        match ProtocolVersion::negotiate(client, self.config.version) {
+            Ok(version) => {
+                self.version = Some(version);
+                if let Some(authenticator) = &self.config.authenticator {
+                    if !version.has_minor(AUTH_MINOR) {
+                        return io_error("Authentication requires minor protocol 3");
+                    }
+                    let nonce = authenticator.nonce()?;
+                    let challenge = AuthChallenge { nonce };
+                    self.send(|writer| challenge.write(writer))?;
+                    self.nonce = Some(challenge.nonce);
+                    self.login_request = Some(signal);
+                    self.state = SessionState::Authenticating;
+                } else {
+                    self.login_request = Some(signal);
+                    self.state = SessionState::LoginRequested;
+                    self.check_login()?;
+                }
+            }
+            Err(rejection) => {
+                self.login_request = Some(signal);
+                self.disconnect(DisconnectCode::ProtocolMismatch, rejection.text(), false)?;
+                self.adapter.on_version_rejected(&rejection);
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn handle_ll_auth_challenge(&mut self, signal: AuthChallenge) -> VioResult { sc_only!() }
+
+    fn handle_ll_auth_response(&mut self, signal: AuthResponse) -> VioResult {
+        let (authenticator, nonce, request) = match (&self.config.authenticator, self.nonce.take(), &self.login_request) {
+            (Some(authenticator), Some(nonce), Some(request)) if self.state == SessionState::Authenticating => (authenticator, nonce, request),
+            _ => return io_error("Received AUTH_RESPONSE without AUTH_CHALLENGE"),
+        };
+        match authenticator.verify(&request.user_id, &nonce, &signal) {
+            Ok(()) => {
+                self.authenticated = true;
+                self.state = SessionState::LoginRequested;
+                self.check_login()?;
+            }
+            Err(error) => {
+                self.disconnect(DisconnectCode::Custom, error.text(), false)?;
+                self.adapter.on_auth_failed(&error);
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn handle_ll_login_accept(&mut self, signal: LoginAccept) -> VioResult { sc_only!() }
+
+    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect) -> VioResult { sc_only!() }
+
+    fn handle_ll_server_disconnect_reason(&mut self, signal: ServerDisconnectReason) -> VioResult { sc_only!() }
+
+    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect) -> VioResult {
+        self.leave();
+        self.adapter.on_disconnect();
+        Result::Ok(())
+    }
+
+    fn handle_ll_ping(&mut self, signal: Ping) -> VioResult {
+        self.send(|writer| Pong {}.write(writer))
+    }
+
+    fn handle_ll_pong(&mut self, signal: Pong) -> VioResult {
+        self.heartbeat.on_pong(self.adapter.now());
+        Result::Ok(())
+    }
+
+    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal) -> VioResult { sc_only!() }
+
+    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal) -> VioResult { sc_only!() }
+
+    fn handle_pk_spawn(&mut self, signal: SpawnSignal) -> VioResult { sc_only!() }
+
+    fn handle_pk_cube_update(&mut self, signal: CubeUpdateSignal) -> VioResult { sc_only!() }
+
+    fn handle_pk_cube_interact(&mut self, signal: CubeInteractSignal) -> VioResult {
+        if self.state != SessionState::Loading && self.state != SessionState::Spawned {
+            return io_error("Received CUBE_INTERACT before LOGIN_ACCEPT");
+        }
+        self.adapter.on_cube_interact(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_flex_motion(&mut self, signal: FlexMotionSignal) -> VioResult { sc_only!() }
+
+    fn handle_pk_user_motion(&mut self, signal: UserMotionSignal) -> VioResult {
+        if self.state != SessionState::Loading && self.state != SessionState::Spawned {
+            return io_error("Received USER_MOTION before LOGIN_ACCEPT");
+        }
+        self.adapter.on_user_motion(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_user_flags(&mut self, signal: UserFlagsSignal) -> VioResult {
+        if self.state != SessionState::Loading && self.state != SessionState::Spawned {
+            return io_error("Received USER_FLAGS before LOGIN_ACCEPT");
+        }
+        self.adapter.on_user_flags(&signal);
+        Result::Ok(())
+    }
+
+    fn handle_pk_flex_flags(&mut self, signal: FlexFlagsSignal) -> VioResult { sc_only!() }
+
+    fn handle_pk_world_switch(&mut self, signal: WorldSwitchSignal) -> VioResult { sc_only!() }
+}
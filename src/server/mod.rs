@@ -0,0 +1,514 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::auth::{AuthError, Authenticator};
+use crate::io::cube::IntPos;
+use crate::io::flex::FlexPos;
+use crate::io::writer::CubeWriter;
+use crate::protocol::{handle_frame, write_frame};
+use crate::protocol::capability::{CAPABILITIES_MINOR, Capability, CapabilitySet};
+use crate::protocol::heartbeat::{Heartbeat, HeartbeatAction, HeartbeatConfig};
+use crate::protocol::ids::PackedId;
+use crate::protocol::ll::disconnect::{DISCONNECT_REASON_MINOR, DisconnectCode, ServerDisconnect, ServerDisconnectReason};
+use crate::protocol::ll::login_accept::LoginAccept;
+use crate::protocol::ll::login_request::{LoginRequest, RESUME_MINOR};
+use crate::protocol::ll::package::PackageWriter;
+use crate::protocol::pk::cube_batch::CubeBatchSignal;
+use crate::protocol::pk::cube_interact::CubeInteractSignal;
+use crate::protocol::pk::spawn::SpawnSignal;
+use crate::protocol::pk::user_flags::UserFlagsSignal;
+use crate::protocol::pk::user_motion::UserMotionSignal;
+use crate::protocol::pk::world_switch::WorldSwitchSignal;
+use crate::protocol::sys_info::SysInfo;
+use crate::protocol::version::{ProtocolVersion, VersionRejection};
+use crate::text::Text;
+use crate::util::{io_error, VioResult};
+
+pub use self::catalog::Catalog;
+pub use self::policy::{AllowList, BanList, LoginDecision, LoginPolicy, MaxPlayers};
+pub use self::resume::ResumptionStore;
+
+pub mod catalog;
+#[cfg(test)]
+mod catalog_test;
+mod handler;
+pub mod policy;
+#[cfg(test)]
+mod policy_test;
+pub mod resume;
+#[cfg(test)]
+mod resume_test;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionState {
+    Initial,
+    LoginRequested,
+    /// AUTH_CHALLENGE is sent, waiting for AUTH_RESPONSE
+    Authenticating,
+    Loading,
+    Spawned,
+    Disconnected,
+}
+
+/// The options of a server session
+#[derive(Clone)]
+pub struct SessionConfig {
+    /// The protocol version served to the client, usually `ProtocolVersion::CURRENT`
+    pub version: ProtocolVersion,
+    /// The optional features supported by the server
+    pub capabilities: CapabilitySet,
+    /// Requires clients to prove that they own their user ID, since minor protocol 3
+    pub authenticator: Option<Arc<dyn Authenticator>>,
+    /// Checked in order when the client requests to log in, see `policy`
+    pub login_policies: Vec<Arc<dyn LoginPolicy>>,
+    /// The PING interval and the number of missed PONGs before a timeout, see `Session::tick`
+    pub heartbeat: HeartbeatConfig,
+    /// Lets clients resume their session after a disconnection, since minor protocol 4
+    pub resumption: Option<Arc<ResumptionStore>>,
+    /// Translates the texts sent to the client, see `Session::translate`
+    pub catalog: Option<Arc<Catalog>>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            version: ProtocolVersion::CURRENT,
+            capabilities: CapabilitySet::EMPTY,
+            authenticator: None,
+            login_policies: Vec::new(),
+            heartbeat: HeartbeatConfig::default(),
+            resumption: None,
+            catalog: None,
+        }
+    }
+}
+
+/// The server side of a connection with one client
+pub struct Session<A> {
+    state: SessionState,
+    login_request: Option<LoginRequest>,
+    sys_info: Option<SysInfo>,
+    config: SessionConfig,
+    version: Option<ProtocolVersion>,
+    capabilities: CapabilitySet,
+    /// The nonce of the pending AUTH_CHALLENGE
+    nonce: Option<Vec<u8>>,
+    authenticated: bool,
+    /// Whether the login policies passed the login request, which must be released with `LoginPolicy::on_leave`
+    admitted: bool,
+    heartbeat: Heartbeat,
+    /// The resume token sent in LOGIN_ACCEPT
+    resume_token: Vec<u8>,
+    resumed: bool,
+    position: Option<FlexPos>,
+    /// The world set by `switch_world`
+    world: Option<String>,
+    loaded_batches: HashSet<IntPos>,
+    adapter: A,
+}
+
+impl<A: SessionAdapter> Session<A> {
+    pub fn new(adapter: A) -> Session<A> {
+        Session::with_config(adapter, SessionConfig::default())
+    }
+
+    pub fn with_config(adapter: A, config: SessionConfig) -> Session<A> {
+        let heartbeat = Heartbeat::new(config.heartbeat);
+        Session {
+            state: SessionState::Initial,
+            login_request: None,
+            sys_info: None,
+            config,
+            version: None,
+            capabilities: CapabilitySet::EMPTY,
+            nonce: None,
+            authenticated: false,
+            admitted: false,
+            heartbeat,
+            resume_token: Vec::new(),
+            resumed: false,
+            position: None,
+            world: None,
+            loaded_batches: HashSet::new(),
+            adapter,
+        }
+    }
+
+    pub fn state(&self) -> SessionState { self.state }
+
+    /// The LOGIN_REQUEST sent by the client, if received
+    pub fn login_request(&self) -> Option<&LoginRequest> { self.login_request.as_ref() }
+
+    /// The client details in the login request, unless the client sent malformed sysInfo
+    pub fn sys_info(&self) -> Option<&SysInfo> { self.sys_info.as_ref() }
+
+    /// Whether the client has proven that it owns the user ID in its login request
+    pub fn is_authenticated(&self) -> bool { self.authenticated }
+
+    /// The protocol version negotiated with the client, once its LOGIN_REQUEST is received
+    pub fn version(&self) -> Option<ProtocolVersion> { self.version }
+
+    /// The features supported by both the client and the server, once the session is accepted
+    pub fn capabilities(&self) -> CapabilitySet { self.capabilities }
+
+    pub fn supports(&self, capability: Capability) -> bool { self.capabilities.contains(capability) }
+
+    /// The language of the client, e.g. `en_US`, once it has sent LOGIN_REQUEST
+    pub fn language(&self) -> Option<&str> {
+        self.login_request.as_ref().map(|request| request.language.as_str())
+    }
+
+    /// Formats `text` in the language of the client with the catalog of the session.
+    /// Without a catalog, the fallback of the text is used.
+    pub fn translate(&self, text: &Text) -> String {
+        match &self.config.catalog {
+            Some(catalog) => catalog.resolve(text, self.language().unwrap_or_else(|| catalog.default_language())),
+            None => text.to_string(),
+        }
+    }
+
+    /// Whether the session continues a suspended session of the client, see `resume`
+    pub fn is_resumed(&self) -> bool { self.resumed }
+
+    /// The last position of the player, set by `spawn` and `set_position`
+    pub fn position(&self) -> Option<&FlexPos> { self.position.as_ref() }
+
+    /// Records the position of the player, so that it is kept when the session is resumed
+    pub fn set_position(&mut self, position: FlexPos) { self.position = Some(position); }
+
+    /// The world that the session was last moved to with `switch_world`
+    pub fn world(&self) -> Option<&str> { self.world.as_deref() }
+
+    /// The batches sent to the client with `send_batch`
+    pub fn loaded_batches(&self) -> &HashSet<IntPos> { &self.loaded_batches }
+
+    /// The round-trip time and missed PONGs of the client
+    pub fn heartbeat(&self) -> &Heartbeat { &self.heartbeat }
+
+    pub fn adapter(&self) -> &A { &self.adapter }
+
+    pub fn adapter_mut(&mut self) -> &mut A { &mut self.adapter }
+
+    /// Handles a binary message received from the client.
+    /// If the message cannot be handled, the client is disconnected with `DisconnectCode::ProtocolViolation`
+    /// and the error is returned.
+    pub fn receive(&mut self, frame: &[u8]) -> VioResult {
+        self.adapter.on_receive(frame);
+        let result = handle_frame(self, frame);
+        if let Err(err) = &result {
+            if self.state != SessionState::Disconnected {
+                // the connection is closed after the error, so a failure to send the reason is not reported
+                let _ = self.disconnect(DisconnectCode::ProtocolViolation, err.to_string(), false);
+            }
+        }
+        result
+    }
+
+    /// Sends PING when it is due, and disconnects the client if it has missed too many PONGs.
+    /// Call this regularly, at least once every `HeartbeatConfig::interval`, e.g. at `Heartbeat::next_ping`.
+    /// Does nothing until the session is accepted.
+    pub fn tick(&mut self) -> VioResult {
+        if self.state != SessionState::Loading && self.state != SessionState::Spawned {
+            return Result::Ok(());
+        }
+        match self.heartbeat.poll(self.adapter.now()) {
+            HeartbeatAction::Idle => Result::Ok(()),
+            HeartbeatAction::Ping(ping) => self.send(|writer| ping.write(writer)),
+            HeartbeatAction::Timeout => {
+                self.disconnect(DisconnectCode::Timeout, Text::translatable("disconnect.timeout", Vec::new(), "Timed out"), true)?;
+                self.adapter.on_timeout();
+                Result::Ok(())
+            }
+        }
+    }
+
+    /// Accepts the pending login request and moves the session to the loading state,
+    /// or to the state of the suspended session if the client resumes one.
+    ///
+    /// The login policies are checked again, since other sessions may have been accepted
+    /// after the login was deferred. If a policy rejects it now, the client is disconnected
+    /// and an error is returned.
+    pub fn accept(&mut self) -> VioResult {
+        if self.state != SessionState::LoginRequested {
+            return io_error("Cannot accept a session without a pending login request");
+        }
+        if let Some(request) = self.login_request.clone() {
+            self.release_policies();
+            if let LoginDecision::Reject { code, reason, rejoin } = self.check_policies(&request) {
+                self.reject(&request, code, reason, rejoin)?;
+                return io_error("The login was rejected by a login policy");
+            }
+        }
+        self.send_accept()
+    }
+
+    /// Sends LOGIN_ACCEPT for the pending login request, which the login policies have accepted
+    fn send_accept(&mut self) -> VioResult {
+        let version = self.config.version;
+        let capabilities = if version.has_minor(CAPABILITIES_MINOR) { self.config.capabilities } else { CapabilitySet::EMPTY };
+        let (resume_token, suspended) = match &self.config.resumption {
+            Some(store) if version.has_minor(RESUME_MINOR) => {
+                let suspended = match &self.login_request {
+                    Some(request) if !request.resume_token.is_empty() => store.resume(&request.resume_token, &request.user_id, self.adapter.now()),
+                    _ => None,
+                };
+                (resume::new_token()?, suspended)
+            }
+            _ => (Vec::new(), None),
+        };
+        let signal = LoginAccept { minor_protocol: version.minor, capabilities, resume_token, resumed: suspended.is_some() };
+        self.send(|writer| signal.write(writer))?;
+        self.resume_token = signal.resume_token;
+        if let Some(request) = &self.login_request {
+            self.capabilities = capabilities.intersection(request.capabilities);
+            for policy in &self.config.login_policies {
+                policy.on_accept(request);
+            }
+        }
+        match suspended {
+            Some(suspended) => {
+                self.resumed = true;
+                self.position = suspended.position;
+                self.world = suspended.world;
+                self.loaded_batches = suspended.loaded_batches;
+                self.state = suspended.state;
+            }
+            None => self.state = SessionState::Loading,
+        }
+        Result::Ok(())
+    }
+
+    /// Sends a package of PKs. `write` is called with the package, e.g. `|package| package.write(|writer| signal.write(writer))`.
+    pub fn send_package<F>(&mut self, write: F) -> VioResult
+        where F: FnOnce(&mut PackageWriter) -> VioResult {
+        if self.state != SessionState::Loading && self.state != SessionState::Spawned {
+            return io_error("Cannot send packages before the session is accepted");
+        }
+        let mut package = PackageWriter::new();
+        write(&mut package)?;
+        self.send(|writer| package.flush(writer))
+    }
+
+    /// Sends a batch and records it as loaded by the client
+    pub fn send_batch(&mut self, batch: &CubeBatchSignal) -> VioResult {
+        self.send_package(|package| package.write(|writer| batch.write(writer)))?;
+        self.loaded_batches.insert(batch.pos);
+        Result::Ok(())
+    }
+
+    /// Sends SPAWN and moves the session to the spawned state
+    pub fn spawn(&mut self, position: FlexPos) -> VioResult {
+        if self.state != SessionState::Loading {
+            return io_error("Cannot spawn outside the loading state");
+        }
+        let signal = SpawnSignal { pos: position };
+        self.send_package(|package| package.write(|writer| signal.write(writer)))?;
+        self.position = Some(position);
+        self.state = SessionState::Spawned;
+        Result::Ok(())
+    }
+
+    /// Sends WORLD_SWITCH and moves the spawned session back to the loading state.
+    /// The batches loaded by the client are forgotten, so the cube dictionary, the batches
+    /// and SPAWN of the new world should be sent next.
+    pub fn switch_world(&mut self, world: &str) -> VioResult {
+        if self.state != SessionState::Spawned {
+            return io_error("Cannot switch worlds outside the spawned state");
+        }
+        if !self.version.is_some_and(|version| version.supports_pk(PackedId::WorldSwitch)) {
+            return io_error("The client does not support WORLD_SWITCH");
+        }
+        let signal = WorldSwitchSignal { world: world.to_string() };
+        self.send_package(|package| package.write(|writer| signal.write(writer)))?;
+        self.world = Some(signal.world);
+        self.position = None;
+        self.loaded_batches.clear();
+        self.state = SessionState::Loading;
+        Result::Ok(())
+    }
+
+    /// Sends SERVER_DISCONNECT_REASON to the client, or SERVER_DISCONNECT without the code
+    /// if the client or the server is older than minor protocol 5.
+    /// The message is translated into the language of the client, see `translate`.
+    /// If `rejoin` is set, the client may resume the session within the grace window of the resumption store.
+    pub fn disconnect<T: Into<Text>>(&mut self, code: DisconnectCode, message: T, rejoin: bool) -> VioResult {
+        if self.state == SessionState::Disconnected {
+            return io_error("Session is already disconnected");
+        }
+        let message = self.translate(&message.into());
+        if self.supports_disconnect_reason() {
+            let signal = ServerDisconnectReason { code, message, rejoin };
+            self.send(|writer| signal.write(writer))?;
+        } else {
+            let signal = ServerDisconnect { reason: message, rejoin };
+            self.send(|writer| signal.write(writer))?;
+        }
+        if rejoin {
+            self.suspend();
+        }
+        self.leave();
+        Result::Ok(())
+    }
+
+    /// Call this when the connection is closed without a disconnect signal.
+    /// The client may resume the session within the grace window of the resumption store.
+    pub fn close(&mut self) {
+        if self.state != SessionState::Disconnected {
+            self.suspend();
+            self.leave();
+        }
+    }
+
+    /// Whether both sides can use SERVER_DISCONNECT_REASON.
+    /// This is known before the version is negotiated, so it also applies to rejections.
+    fn supports_disconnect_reason(&self) -> bool {
+        let server = self.config.version;
+        match &self.login_request {
+            Some(request) => request.major_protocol == server.major
+                && request.minor_protocol >= DISCONNECT_REASON_MINOR
+                && server.has_minor(DISCONNECT_REASON_MINOR),
+            None => false,
+        }
+    }
+
+    /// Keeps the state of an accepted session in the resumption store
+    fn suspend(&mut self) {
+        let accepted = self.state == SessionState::Loading || self.state == SessionState::Spawned;
+        if !accepted || self.resume_token.is_empty() {
+            return;
+        }
+        if let (Some(store), Some(request)) = (&self.config.resumption, &self.login_request) {
+            let suspended = resume::SuspendedSession {
+                user_id: request.user_id,
+                state: self.state,
+                position: self.position,
+                world: self.world.clone(),
+                loaded_batches: self.loaded_batches.clone(),
+            };
+            store.suspend(self.resume_token.clone(), suspended, self.adapter.now());
+        }
+    }
+
+    /// Applies the login policies to the pending login request
+    fn check_login(&mut self) -> VioResult {
+        let request = match &self.login_request {
+            Some(request) => request.clone(),
+            None => return io_error("Cannot check a login without a login request"),
+        };
+        let decision = if self.config.login_policies.is_empty() {
+            LoginDecision::Defer
+        } else {
+            self.check_policies(&request)
+        };
+        match decision {
+            LoginDecision::Accept => {
+                self.send_accept()?;
+                self.adapter.on_login_accepted(&request);
+            }
+            LoginDecision::Reject { code, reason, rejoin } => self.reject(&request, code, reason, rejoin)?,
+            LoginDecision::Defer => self.adapter.on_login_request(&request),
+        }
+        Result::Ok(())
+    }
+
+    fn check_policies(&mut self, request: &LoginRequest) -> LoginDecision {
+        let decision = policy::check_all(self.config.login_policies.iter().map(|policy| policy.as_ref()), request);
+        self.admitted = !matches!(decision, LoginDecision::Reject { .. });
+        decision
+    }
+
+    /// Releases what the login policies reserved for the login request
+    fn release_policies(&mut self) {
+        if let (true, Some(request)) = (self.admitted, &self.login_request) {
+            for policy in &self.config.login_policies {
+                policy.on_leave(request);
+            }
+        }
+        self.admitted = false;
+    }
+
+    /// Disconnects the client for a login policy
+    fn reject(&mut self, request: &LoginRequest, code: DisconnectCode, reason: Text, rejoin: bool) -> VioResult {
+        self.disconnect(code, reason.clone(), rejoin)?;
+        self.adapter.on_login_rejected(request, code, &reason);
+        Result::Ok(())
+    }
+
+    /// Moves the session to the disconnected state, releasing its place in the login policies
+    fn leave(&mut self) {
+        self.release_policies();
+        self.state = SessionState::Disconnected;
+    }
+
+    fn send<F>(&mut self, write: F) -> VioResult
+        where F: FnOnce(&mut CubeWriter<Vec<u8>>) -> VioResult {
+        let frame = write_frame(write)?;
+        self.adapter.send(frame)
+    }
+}
+
+#[allow(unused_variables)]
+pub trait SessionAdapter {
+    /// Sends a binary message to the client
+    fn send(&mut self, frame: Vec<u8>) -> VioResult;
+
+    /// Called with every binary message received from the client, before it is handled
+    fn on_receive(&mut self, frame: &[u8]) {}
+
+    /// The current time, used to measure the round-trip time
+    fn now(&self) -> Instant { Instant::now() }
+
+    /// Called when the session disconnects a client that cannot use the protocol version of the server.
+    /// `on_login_request` is not called for such clients.
+    fn on_version_rejected(&mut self, rejection: &VersionRejection) {}
+
+    /// Called when the session disconnects a client that fails to authenticate
+    fn on_auth_failed(&mut self, error: &AuthError) {}
+
+    /// Called when the client requests to log in with a compatible protocol version,
+    /// after it is authenticated if the session has an authenticator,
+    /// unless the login policies accept or reject it.
+    /// The server should respond by calling `Session::accept` or `Session::disconnect`.
+    fn on_login_request(&mut self, request: &LoginRequest) {}
+
+    /// Called when the login policies accept the session, after LOGIN_ACCEPT is sent
+    fn on_login_accepted(&mut self, request: &LoginRequest) {}
+
+    /// Called when a login policy disconnects the client with `code` and `reason`
+    fn on_login_rejected(&mut self, request: &LoginRequest, code: DisconnectCode, reason: &Text) {}
+
+    /// Called when the user clicks on a cube
+    fn on_cube_interact(&mut self, signal: &CubeInteractSignal) {}
+
+    /// Called when the user changes walking direction
+    fn on_user_motion(&mut self, signal: &UserMotionSignal) {}
+
+    /// Called when the user presses or releases a movement key
+    fn on_user_flags(&mut self, signal: &UserFlagsSignal) {}
+
+    /// Called when the session disconnects a client that has missed too many PONGs
+    fn on_timeout(&mut self) {}
+
+    /// Called when the client disconnects
+    fn on_disconnect(&mut self) {}
+}
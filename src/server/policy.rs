@@ -0,0 +1,207 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Decides whether a login request is accepted.
+//!
+//! The policies of `SessionConfig::login_policies` are checked in order once the client is
+//! authenticated. The first rejection disconnects the client. If every policy accepts,
+//! the session is accepted without waiting for the server, and if any of them defers,
+//! the decision is left to `SessionAdapter::on_login_request`.
+//! `Session::accept` checks the policies again, so that a deferred login that a policy
+//! rejects by then, e.g. because the server has filled up, is still rejected.
+//!
+//! A policy may reserve a resource in `check`, such as a player slot, so that concurrent logins
+//! cannot all pass it. The reservation is released with `on_leave` when the login does not
+//! pass the other policies, when a deferred login is checked again, or when the session ends.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::protocol::ll::disconnect::DisconnectCode;
+use crate::protocol::ll::login_request::LoginRequest;
+use crate::text::Text;
+
+/// The result of `LoginPolicy::check`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoginDecision {
+    Accept,
+    /// Disconnects the client with `code` and `reason`; `rejoin` tells the client whether it may try again
+    Reject { code: DisconnectCode, reason: Text, rejoin: bool },
+    /// Leaves the decision to the other policies or to the server
+    Defer,
+}
+
+impl LoginDecision {
+    pub fn reject<T: Into<Text>>(code: DisconnectCode, reason: T, rejoin: bool) -> LoginDecision {
+        LoginDecision::Reject { code, reason: reason.into(), rejoin }
+    }
+}
+
+#[allow(unused_variables)]
+pub trait LoginPolicy: Send + Sync {
+    /// Decides whether the login is accepted. Unless this rejects the login, `on_leave` is called for it later.
+    fn check(&self, request: &LoginRequest) -> LoginDecision;
+
+    /// Called when a session is accepted, either because every policy accepted it or by the server
+    fn on_accept(&self, request: &LoginRequest) {}
+
+    /// Called when a login that this policy did not reject is rejected by another policy,
+    /// checked again, or disconnected, whether it was accepted or not
+    fn on_leave(&self, request: &LoginRequest) {}
+}
+
+impl<F> LoginPolicy for F where F: Fn(&LoginRequest) -> LoginDecision + Send + Sync {
+    fn check(&self, request: &LoginRequest) -> LoginDecision {
+        self(request)
+    }
+}
+
+/// Checks the request against `policies` in order.
+/// If a policy rejects the request, `on_leave` is called on the policies before it.
+pub fn check_all<'a, I>(policies: I, request: &LoginRequest) -> LoginDecision
+    where I: IntoIterator<Item = &'a dyn LoginPolicy> {
+    let mut decision = LoginDecision::Accept;
+    let mut passed: Vec<&dyn LoginPolicy> = Vec::new();
+    for policy in policies {
+        match policy.check(request) {
+            LoginDecision::Accept => {}
+            LoginDecision::Defer => decision = LoginDecision::Defer,
+            reject => {
+                for policy in passed {
+                    policy.on_leave(request);
+                }
+                return reject;
+            }
+        }
+        passed.push(policy);
+    }
+    decision
+}
+
+/// Only accepts the listed user IDs
+#[derive(Debug, Default)]
+pub struct AllowList {
+    user_ids: RwLock<HashSet<[u8; 20]>>,
+}
+
+impl AllowList {
+    pub fn new<I: IntoIterator<Item = [u8; 20]>>(user_ids: I) -> AllowList {
+        AllowList { user_ids: RwLock::new(user_ids.into_iter().collect()) }
+    }
+
+    pub fn allow(&self, user_id: [u8; 20]) {
+        self.user_ids.write().expect("poisoned lock").insert(user_id);
+    }
+
+    /// Does not disconnect the user if they are already online
+    pub fn disallow(&self, user_id: &[u8; 20]) {
+        self.user_ids.write().expect("poisoned lock").remove(user_id);
+    }
+
+    pub fn contains(&self, user_id: &[u8; 20]) -> bool {
+        self.user_ids.read().expect("poisoned lock").contains(user_id)
+    }
+}
+
+impl LoginPolicy for AllowList {
+    fn check(&self, request: &LoginRequest) -> LoginDecision {
+        if self.contains(&request.user_id) {
+            LoginDecision::Accept
+        } else {
+            let reason = Text::translatable("login.notAllowed", Vec::new(), "You are not on the allow list of this server");
+            LoginDecision::reject(DisconnectCode::Custom, reason, false)
+        }
+    }
+}
+
+/// Rejects the listed user IDs, each with its own reason
+#[derive(Debug, Default)]
+pub struct BanList {
+    bans: RwLock<HashMap<[u8; 20], String>>,
+}
+
+impl BanList {
+    pub fn new() -> BanList { BanList::default() }
+
+    /// Does not disconnect the user if they are already online
+    pub fn ban(&self, user_id: [u8; 20], reason: &str) {
+        self.bans.write().expect("poisoned lock").insert(user_id, reason.to_owned());
+    }
+
+    /// Returns whether the user was banned
+    pub fn unban(&self, user_id: &[u8; 20]) -> bool {
+        self.bans.write().expect("poisoned lock").remove(user_id).is_some()
+    }
+
+    /// The ban reason of the user, if banned
+    pub fn reason(&self, user_id: &[u8; 20]) -> Option<String> {
+        self.bans.read().expect("poisoned lock").get(user_id).cloned()
+    }
+}
+
+impl LoginPolicy for BanList {
+    fn check(&self, request: &LoginRequest) -> LoginDecision {
+        match self.reason(&request.user_id) {
+            Some(reason) => LoginDecision::reject(DisconnectCode::Banned, reason, false),
+            None => LoginDecision::Accept,
+        }
+    }
+}
+
+/// Rejects new sessions while `limit` sessions are online.
+/// `check` reserves a slot atomically, which is held until `on_leave`, so the count includes
+/// every session that passed `check` and has not left yet, whether it is accepted or still deferred.
+/// The same `MaxPlayers` must be shared by all sessions of a server.
+#[derive(Debug)]
+pub struct MaxPlayers {
+    limit: usize,
+    online: AtomicUsize,
+}
+
+impl MaxPlayers {
+    pub fn new(limit: usize) -> MaxPlayers {
+        MaxPlayers { limit, online: AtomicUsize::new(0) }
+    }
+
+    pub fn limit(&self) -> usize { self.limit }
+
+    /// The number of sessions holding a slot, i.e. that passed `check` and have not left yet
+    pub fn online(&self) -> usize { self.online.load(Ordering::SeqCst) }
+}
+
+#[allow(unused_variables)]
+impl LoginPolicy for MaxPlayers {
+    fn check(&self, request: &LoginRequest) -> LoginDecision {
+        let reserved = self.online.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |online| {
+            if online < self.limit { Some(online + 1) } else { None }
+        });
+        match reserved {
+            Ok(_) => LoginDecision::Accept,
+            Err(_) => {
+                let reason = Text::translatable("login.serverFull", Vec::new(), "The server is full");
+                LoginDecision::reject(DisconnectCode::Custom, reason, true)
+            }
+        }
+    }
+
+    fn on_leave(&self, request: &LoginRequest) {
+        self.online.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use crate::protocol::capability::CapabilitySet;
+use crate::protocol::ll::disconnect::DisconnectCode;
+use crate::protocol::ll::login_request::LoginRequest;
+use crate::protocol::version::ProtocolVersion;
+use crate::server::policy::{AllowList, BanList, check_all, LoginDecision, LoginPolicy, MaxPlayers};
+use crate::text::Text;
+
+fn request(user_id: u8, language: &str) -> LoginRequest {
+    LoginRequest {
+        major_protocol: ProtocolVersion::CURRENT.major,
+        minor_protocol: ProtocolVersion::CURRENT.minor,
+        username: "Steve".to_owned(),
+        user_id: [user_id; 20],
+        language: language.to_owned(),
+        sys_info: "{}".to_owned(),
+        capabilities: CapabilitySet::EMPTY,
+        resume_token: Vec::new(),
+    }
+}
+
+#[test]
+fn allow_list() {
+    let list = AllowList::new(vec![[1; 20]]);
+    assert_eq!(list.check(&request(1, "en_US")), LoginDecision::Accept);
+    let reason = Text::translatable("login.notAllowed", Vec::new(), "You are not on the allow list of this server");
+    assert_eq!(list.check(&request(2, "en_US")), LoginDecision::reject(DisconnectCode::Custom, reason, false));
+
+    list.allow([2; 20]);
+    list.disallow(&[1; 20]);
+    assert!(!list.contains(&[1; 20]));
+    assert_eq!(list.check(&request(2, "en_US")), LoginDecision::Accept);
+}
+
+#[test]
+fn ban_list() {
+    let list = BanList::new();
+    list.ban([1; 20], "Griefing");
+    assert_eq!(list.check(&request(1, "en_US")), LoginDecision::reject(DisconnectCode::Banned, "Griefing", false));
+    assert_eq!(list.check(&request(2, "en_US")), LoginDecision::Accept);
+
+    assert!(list.unban(&[1; 20]));
+    assert!(!list.unban(&[1; 20]));
+    assert_eq!(list.reason(&[1; 20]), None);
+    assert_eq!(list.check(&request(1, "en_US")), LoginDecision::Accept);
+}
+
+#[test]
+fn max_players() {
+    let policy = MaxPlayers::new(1);
+    assert_eq!(policy.check(&request(1, "en_US")), LoginDecision::Accept);
+    policy.on_accept(&request(1, "en_US"));
+    assert_eq!(policy.online(), 1);
+    let reason = Text::translatable("login.serverFull", Vec::new(), "The server is full");
+    assert_eq!(policy.check(&request(2, "en_US")), LoginDecision::reject(DisconnectCode::Custom, reason, true));
+    policy.on_leave(&request(1, "en_US"));
+    assert_eq!(policy.check(&request(2, "en_US")), LoginDecision::Accept);
+}
+
+#[test]
+fn max_players_concurrent() {
+    let policy = Arc::new(MaxPlayers::new(4));
+    let barrier = Arc::new(Barrier::new(16));
+    let threads: Vec<_> = (0..16).map(|user_id| {
+        let policy = policy.clone();
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            barrier.wait();
+            policy.check(&request(user_id, "en_US")) == LoginDecision::Accept
+        })
+    }).collect();
+    let accepted = threads.into_iter().map(|thread| thread.join().unwrap()).filter(|&accepted| accepted).count();
+    assert_eq!(accepted, 4);
+    assert_eq!(policy.online(), 4);
+}
+
+#[test]
+fn release_on_reject() {
+    let max_players = MaxPlayers::new(1);
+    let bans = BanList::new();
+    bans.ban([1; 20], "Banned");
+    let policies: [&dyn LoginPolicy; 2] = [&max_players, &bans];
+    assert_eq!(check_all(policies.iter().copied(), &request(1, "en_US")), LoginDecision::reject(DisconnectCode::Banned, "Banned", false));
+    assert_eq!(max_players.online(), 0);
+    assert_eq!(check_all(policies.iter().copied(), &request(2, "en_US")), LoginDecision::Accept);
+    assert_eq!(max_players.online(), 1);
+}
+
+#[test]
+fn check_in_order() {
+    let bans = BanList::new();
+    bans.ban([1; 20], "Banned");
+    let english = |request: &LoginRequest| if request.language.starts_with("en_") {
+        LoginDecision::Accept
+    } else {
+        LoginDecision::reject(DisconnectCode::Custom, "English only", false)
+    };
+    let defer = |_: &LoginRequest| LoginDecision::Defer;
+    let policies: [&dyn LoginPolicy; 3] = [&bans, &english, &defer];
+
+    assert_eq!(check_all(policies.iter().copied(), &request(1, "de_DE")), LoginDecision::reject(DisconnectCode::Banned, "Banned", false));
+    assert_eq!(check_all(policies.iter().copied(), &request(2, "de_DE")), LoginDecision::reject(DisconnectCode::Custom, "English only", false));
+    assert_eq!(check_all(policies.iter().copied(), &request(2, "en_GB")), LoginDecision::Defer);
+    assert_eq!(check_all(policies[..2].iter().copied(), &request(2, "en_GB")), LoginDecision::Accept);
+}
@@ -0,0 +1,93 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Remembers disconnected sessions so that their clients can resume them.
+//!
+//! When an accepted session is lost or disconnected with rejoin, its state is kept under
+//! the resume token sent in LOGIN_ACCEPT until the grace window elapses.
+//! A client that logs in again with the token skips loading the world again.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::io::cube::IntPos;
+use crate::io::flex::FlexPos;
+use crate::server::SessionState;
+use crate::util::{make_io_error, IoResult};
+
+/// The number of random bytes in a resume token
+pub const TOKEN_SIZE: usize = 16;
+
+/// The suspended sessions of a server, shared by all its sessions through `SessionConfig::resumption`
+#[derive(Debug)]
+pub struct ResumptionStore {
+    grace: Duration,
+    /// The suspended sessions by token, with the time when they expire
+    sessions: Mutex<HashMap<Vec<u8>, (Instant, SuspendedSession)>>,
+}
+
+/// The state that a resumed session continues with
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SuspendedSession {
+    pub user_id: [u8; 20],
+    pub state: SessionState,
+    pub position: Option<FlexPos>,
+    pub world: Option<String>,
+    pub loaded_batches: HashSet<IntPos>,
+}
+
+impl ResumptionStore {
+    pub fn new(grace: Duration) -> ResumptionStore {
+        ResumptionStore { grace, sessions: Mutex::new(HashMap::new()) }
+    }
+
+    /// How long a disconnected session can be resumed
+    pub fn grace(&self) -> Duration { self.grace }
+
+    /// The number of sessions that can still be resumed at `now`
+    pub fn len(&self, now: Instant) -> usize {
+        self.sessions.lock().expect("poisoned lock").values().filter(|(expires, _)| *expires > now).count()
+    }
+
+    pub fn is_empty(&self, now: Instant) -> bool { self.len(now) == 0 }
+
+    pub(crate) fn suspend(&self, token: Vec<u8>, session: SuspendedSession, now: Instant) {
+        let mut sessions = self.sessions.lock().expect("poisoned lock");
+        sessions.retain(|_, (expires, _)| *expires > now);
+        sessions.insert(token, (now + self.grace, session));
+    }
+
+    /// Takes the session of `token` if it belongs to `user_id` and has not expired
+    pub(crate) fn resume(&self, token: &[u8], user_id: &[u8; 20], now: Instant) -> Option<SuspendedSession> {
+        let mut sessions = self.sessions.lock().expect("poisoned lock");
+        match sessions.get(token) {
+            Some((_, session)) if session.user_id == *user_id => {}
+            _ => return None,
+        }
+        sessions.remove(token).filter(|(expires, _)| *expires > now).map(|(_, session)| session)
+    }
+}
+
+/// Generates a token from the random number generator of the operating system
+pub(crate) fn new_token() -> IoResult<Vec<u8>> {
+    let mut token = vec![0; TOKEN_SIZE];
+    getrandom::getrandom(&mut token).map_err(|err| make_io_error(&err.to_string()))?;
+    Result::Ok(token)
+}
@@ -0,0 +1,74 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::io::cube::IntPos;
+use crate::server::SessionState;
+use crate::server::resume::{new_token, ResumptionStore, SuspendedSession, TOKEN_SIZE};
+
+fn suspended(user_id: u8) -> SuspendedSession {
+    SuspendedSession {
+        user_id: [user_id; 20],
+        state: SessionState::Spawned,
+        position: None,
+        world: Some("lobby".to_string()),
+        loaded_batches: vec![IntPos::new(0, 1, 0)].into_iter().collect::<HashSet<_>>(),
+    }
+}
+
+#[test]
+fn resume_once() {
+    let now = Instant::now();
+    let store = ResumptionStore::new(Duration::from_secs(60));
+    store.suspend(vec![1], suspended(1), now);
+    assert_eq!(store.len(now), 1);
+    assert_eq!(store.resume(&[2], &[1; 20], now), None);
+    assert_eq!(store.resume(&[1], &[1; 20], now), Some(suspended(1)));
+    assert_eq!(store.resume(&[1], &[1; 20], now), None);
+    assert!(store.is_empty(now));
+}
+
+#[test]
+fn resume_other_user() {
+    let now = Instant::now();
+    let store = ResumptionStore::new(Duration::from_secs(60));
+    store.suspend(vec![1], suspended(1), now);
+    assert_eq!(store.resume(&[1], &[2; 20], now), None);
+    assert_eq!(store.resume(&[1], &[1; 20], now), Some(suspended(1)));
+}
+
+#[test]
+fn expire() {
+    let now = Instant::now();
+    let grace = Duration::from_secs(60);
+    let store = ResumptionStore::new(grace);
+    store.suspend(vec![1], suspended(1), now);
+    store.suspend(vec![2], suspended(2), now + grace / 2);
+    assert_eq!(store.len(now + grace), 1);
+    assert_eq!(store.resume(&[1], &[1; 20], now + grace), None);
+    assert_eq!(store.resume(&[2], &[2; 20], now + grace), Some(suspended(2)));
+}
+
+#[test]
+fn random_token() {
+    let token = new_token().unwrap();
+    assert_eq!(token.len(), TOKEN_SIZE);
+    assert_ne!(token, new_token().unwrap());
+}
@@ -0,0 +1,96 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Text shown to the player, which can be translated into the language of the player.
+//!
+//! A translatable text has a key, which looks up a template in the translation catalog of the
+//! language, and arguments that replace the `{0}`, `{1}`, ... placeholders of the template.
+//! If the key has no translation, the fallback is used as the template instead.
+
+use std::fmt;
+
+#[cfg(test)]
+mod text_test;
+
+/// A text that the server translates into the language of the session before sending it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Text {
+    /// The translation key, empty for a literal text
+    pub key: String,
+    /// The values of the placeholders
+    pub args: Vec<String>,
+    /// The template used if the key has no translation
+    pub fallback: String,
+}
+
+impl Text {
+    /// A text that is never translated
+    pub fn literal(text: &str) -> Text {
+        Text { key: String::new(), args: Vec::new(), fallback: text.to_owned() }
+    }
+
+    pub fn translatable(key: &str, args: Vec<String>, fallback: &str) -> Text {
+        Text { key: key.to_owned(), args, fallback: fallback.to_owned() }
+    }
+
+    pub fn is_literal(&self) -> bool { self.key.is_empty() }
+
+    /// Replaces the placeholders in `template` with the arguments.
+    /// Placeholders without an argument and other braces are kept as they are.
+    pub fn format(&self, template: &str) -> String {
+        let mut ret = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            ret.push_str(&rest[..open]);
+            rest = &rest[open..];
+            let arg = rest.find('}')
+                .and_then(|close| rest[1..close].parse::<usize>().ok().map(|index| (index, close)))
+                .and_then(|(index, close)| self.args.get(index).map(|arg| (arg, close)));
+            match arg {
+                Some((arg, close)) => {
+                    ret.push_str(arg);
+                    rest = &rest[close + 1..];
+                }
+                None => {
+                    ret.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        ret.push_str(rest);
+        ret
+    }
+}
+
+/// Formats the fallback, i.e. the text without translation
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.format(&self.fallback))
+    }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Text { Text::literal(text) }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Text {
+        Text { key: String::new(), args: Vec::new(), fallback: text }
+    }
+}
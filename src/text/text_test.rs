@@ -0,0 +1,37 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::text::Text;
+
+#[test]
+fn format() {
+    let text = Text::translatable("login.banned", vec!["Steve".to_owned(), "griefing".to_owned()], "{0} is banned for {1}");
+    assert_eq!(text.to_string(), "Steve is banned for griefing");
+    assert_eq!(text.format("{1}: {0}"), "griefing: Steve");
+    assert_eq!(text.format("{2} {x} {} {0"), "{2} {x} {} {0");
+    assert_eq!(text.format("{{0}}"), "{Steve}");
+}
+
+#[test]
+fn literal() {
+    let text = Text::from("Bye {0}");
+    assert!(text.is_literal());
+    assert_eq!(text, Text::literal("Bye {0}"));
+    assert_eq!(text.to_string(), "Bye {0}");
+}
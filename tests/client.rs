@@ -18,25 +18,152 @@
  */
 
 extern crate cube_engine;
-extern crate websocket;
-
-//use websocket::client::async::ClientNew;
-//use websocket::ClientBuilder;
-//use websocket::stream::async::TcpStream;
 
 mod common;
 
+use std::time::Duration;
+
+use cube_engine::client::{Backoff, Client, ClientConfig, ClientState};
+use cube_engine::cube::def::CubeDef;
+use cube_engine::io::cube::{CubePos, FloatPos, IntPos};
+use cube_engine::io::flex::FlexPos;
+use cube_engine::protocol::capability::CapabilitySet;
+use cube_engine::protocol::ll::disconnect::{DisconnectCode, LL_CLIENT_DISCONNECT, ServerDisconnect};
+use cube_engine::protocol::ll::login_accept::LoginAccept;
+use cube_engine::protocol::handler::SignalHandler;
+use cube_engine::protocol::pk::cube_batch::CubeBatchSignal;
+use cube_engine::protocol::pk::cube_dict::CubeDictSignal;
+use cube_engine::protocol::pk::cube_update::CubeUpdateSignal;
+use cube_engine::protocol::pk::flex_flags::FlexFlagsSignal;
+use cube_engine::protocol::pk::flex_motion::FlexMotionSignal;
+use cube_engine::protocol::version::ProtocolVersion;
+use cube_engine::protocol::write_frame;
+use cube_engine::server::{Session, SessionConfig};
+
+use common::replay::{deliver, fixture_client_config, login, Loopback};
+
 #[test]
 fn test_hex() {
-    let tests = common::read::read_tests().expect("Error reading tests");
-//    let mut port = 8765;
-    for (name, test) in tests {
-//        let mut client = ClientBuilder::new(("ws://127.0.0.1:".to_owned() + port.to_string().as_str()).as_str())
-//            .expect("Failed creating client");
-//        client.add_protocol("cube-pump");
-//        test.run_client(client.async_connect_insecure());
-        // TODO
-        println!("{}", test);
-//        port += 1;
+    let tests = common::read_tests();
+    assert!(!tests.is_empty());
+    for test in tests {
+        if let Err(err) = common::replay::replay_client(&test) {
+            panic!("Test \"{}\" failed: {}", test.name, err);
+        }
+    }
+}
+
+#[test]
+fn reject_newer_server() {
+    let mut client = Client::new(Loopback::default(), fixture_client_config());
+    client.connect().unwrap();
+    client.adapter_mut().sent.clear();
+
+    let minor_protocol = ProtocolVersion::CURRENT.minor + 1;
+    let accept = LoginAccept { minor_protocol, capabilities: CapabilitySet::EMPTY, resume_token: Vec::new(), resumed: false };
+    client.receive(&write_frame(|writer| accept.write(writer)).unwrap()).unwrap();
+    assert_eq!(client.state(), ClientState::Disconnected);
+    assert_eq!(client.version(), None);
+    assert_eq!(client.adapter().sent, vec![vec![LL_CLIENT_DISCONNECT]]);
+}
+
+#[test]
+fn rejoin_with_backoff() {
+    let backoff = Backoff { initial: Duration::from_secs(1), max: Duration::from_secs(3), max_attempts: 3 };
+    let mut client = Client::new(Loopback::default(), ClientConfig { backoff, ..fixture_client_config() });
+    assert!(client.reconnect().is_err());
+    client.connect().unwrap();
+
+    let kick = |rejoin: bool| write_frame(|writer| ServerDisconnect { reason: "Restarting".to_owned(), rejoin }.write(writer)).unwrap();
+    client.receive(&kick(true)).unwrap();
+    let mut delays = Vec::new();
+    while let Some(delay) = client.rejoin_delay() {
+        delays.push(delay);
+        client.reconnect().unwrap();
+        assert_eq!(client.state(), ClientState::LoginRequested);
+        client.connection_lost();
     }
+    assert_eq!(delays, vec![Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(3)]);
+
+    let mut client = Client::new(Loopback::default(), fixture_client_config());
+    client.connect().unwrap();
+    client.receive(&kick(false)).unwrap();
+    assert_eq!(client.rejoin_delay(), None);
+    assert!(client.reconnect().is_err());
+}
+
+#[test]
+fn receive_flex_motion() {
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig::default());
+    let pos = FlexPos::from_world(FloatPos { x: 8.0, y: 1.0, z: 8.0 }, 0.0, 0.0);
+    let motion = FlexMotionSignal { event_time: 42, pos, velocity: FloatPos { x: 1.0, y: 0.0, z: 0.0 } };
+    session.send_package(|package| package.write(|writer| motion.write(writer))).unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.adapter().events, vec!["on_flex_motion(42)"]);
+}
+
+#[test]
+fn receive_game_play() {
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig::default());
+    let update = CubeUpdateSignal { pos: CubePos::from_world(IntPos::new(1, 2, 3)), cube: 7 };
+    let flags = FlexFlagsSignal { crouch: true };
+    session.send_package(|package| {
+        package.write(|writer| update.write(writer))?;
+        package.write(|writer| flags.write(writer))
+    }).unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.adapter().events, vec!["on_cube_update(7)", "on_flex_flags(true)"]);
+}
+
+#[test]
+fn load_world_after_switch() {
+    let position = FlexPos::from_world(FloatPos { x: 8.0, y: 1.0, z: 8.0 }, 0.0, 0.0);
+    let dict = CubeDictSignal { defs: vec![Box::new(CubeDef { id: 1, name: "CubePump.Stone".to_owned() })] };
+    let load = |session: &mut Session<Loopback>, client: &mut Client<Loopback>, pos: IntPos| {
+        session.send_package(|package| package.write(|writer| dict.write(writer))).unwrap();
+        session.send_batch(&CubeBatchSignal { pos, payload: [1; 4096] }).unwrap();
+        session.spawn(position).unwrap();
+        deliver(session, client);
+    };
+
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig::default());
+    load(&mut session, &mut client, IntPos::new(0, 0, 0));
+    assert_eq!(client.state(), ClientState::Spawned);
+
+    session.switch_world("nether").unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.state(), ClientState::Loading);
+    load(&mut session, &mut client, IntPos::new(16, 0, 0));
+    assert_eq!(client.state(), ClientState::Spawned);
+    assert_eq!(client.adapter().events, vec![
+        "on_cube_dict(1)", "on_cube_batch(0, 0, 0)",
+        "on_world_switch(nether)", "on_cube_dict(1)", "on_cube_batch(16, 0, 0)",
+    ]);
+}
+
+#[test]
+fn reject_batch_before_login() {
+    let mut client = Client::new(Loopback::default(), fixture_client_config());
+    client.connect().unwrap();
+    let mut session = login(fixture_client_config(), SessionConfig::default()).1;
+    session.send_batch(&CubeBatchSignal { pos: IntPos::new(0, 0, 0), payload: [0; 4096] }).unwrap();
+    let frame = session.adapter_mut().sent.pop_front().unwrap();
+    assert!(client.receive(&frame).is_err());
+    assert_eq!(client.state(), ClientState::LoginRequested);
+}
+
+#[test]
+fn reject_game_play_outside_session() {
+    let update = CubeUpdateSignal { pos: CubePos::from_world(IntPos::new(1, 2, 3)), cube: 7 };
+    let mut client = Client::new(Loopback::default(), fixture_client_config());
+    assert!(client.handle_pk_cube_update(update.clone()).is_err());
+    assert!(client.handle_pk_flex_flags(FlexFlagsSignal { crouch: true }).is_err());
+
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig::default());
+    session.disconnect(DisconnectCode::Kicked, "Bye", false).unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.state(), ClientState::Disconnected);
+    assert!(client.handle_pk_cube_update(update).is_err());
+    assert!(client.handle_pk_flex_flags(FlexFlagsSignal { crouch: true }).is_err());
+    assert_eq!(client.adapter().events, vec![format!("on_disconnect({}, Bye, false)", DisconnectCode::Kicked)]);
 }
@@ -17,39 +17,14 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-extern crate hex;
+#![allow(dead_code)]
 
-use std::fmt::{Display, Formatter, Error};
+use std::path::Path;
 
-pub mod read;
-pub mod preprocess;
-pub mod lexer;
-pub mod parser;
+use cube_engine::conformance::{Conversation, read_conversations};
 
-pub struct Test {
-    pub name: String,
-    pub steps: Vec<Step<u8>>,
-}
-
-impl Display for Test {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(f, "Test {}: {{\n", self.name)?;
-        for step in &self.steps {
-            write!(f, "  {},", step)?;
-        }
-        write!(f, "}}")?;
-        Result::Ok(())
-    }
-}
-
-pub struct Step<S> {
-    pub from_server: bool,
-    pub buffer: Vec<S>,
-}
+pub mod replay;
 
-impl Display for Step<u8> {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let buf = hex::encode(&self.buffer);
-        write!(f, "from_server: {}, buffer:\n{}\n", self.from_server, buf)
-    }
+pub fn read_tests() -> Vec<Conversation> {
+    read_conversations(Path::new("tests/hex")).expect("Error reading tests")
 }
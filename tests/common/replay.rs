@@ -0,0 +1,346 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+extern crate hex;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+use cube_engine::auth::{AuthError, Authenticator, Keypair, KeypairAuthenticator};
+use cube_engine::client::{Backoff, Client, ClientAdapter, ClientConfig};
+use cube_engine::conformance::{Conversation, disassemble};
+use cube_engine::protocol::capability::CapabilitySet;
+use cube_engine::protocol::handle_frame;
+use cube_engine::protocol::handler::SignalHandler;
+use cube_engine::protocol::ll::auth::{AuthChallenge, AuthResponse, LL_AUTH_CHALLENGE};
+use cube_engine::protocol::ll::disconnect::{ClientDisconnect, DisconnectCode, ServerDisconnect, ServerDisconnectReason};
+use cube_engine::protocol::ll::login_accept::{LL_LOGIN_ACCEPT, LoginAccept};
+use cube_engine::io::reader::CubeReader;
+use cube_engine::protocol::ll::login_request::{LL_LOGIN_REQUEST, LoginRequest};
+use cube_engine::protocol::ll::ping::{Ping, Pong};
+use cube_engine::protocol::pk::cube_batch::CubeBatchSignal;
+use cube_engine::protocol::pk::cube_dict::CubeDictSignal;
+use cube_engine::protocol::pk::flex_motion::FlexMotionSignal;
+use cube_engine::protocol::pk::cube_update::CubeUpdateSignal;
+use cube_engine::protocol::pk::cube_interact::CubeInteractSignal;
+use cube_engine::protocol::pk::user_motion::UserMotionSignal;
+use cube_engine::protocol::pk::user_flags::UserFlagsSignal;
+use cube_engine::protocol::pk::flex_flags::FlexFlagsSignal;
+use cube_engine::protocol::pk::world_switch::WorldSwitchSignal;
+use cube_engine::protocol::pk::spawn::SpawnSignal;
+use cube_engine::protocol::version::ProtocolVersion;
+use cube_engine::server::{Session, SessionAdapter, SessionConfig};
+use cube_engine::util::{io_error, io_error_f, IoResult, VioResult};
+
+
+/// An in-memory connection that queues every sent frame and records the adapter callbacks
+pub struct Loopback {
+    pub sent: VecDeque<Vec<u8>>,
+    /// The time reported to the session, which only moves when the test advances it
+    pub now: Instant,
+    /// The callbacks called on the adapter, e.g. `on_world_switch(nether)`
+    pub events: Vec<String>,
+}
+
+impl Default for Loopback {
+    fn default() -> Loopback {
+        Loopback { sent: VecDeque::new(), now: Instant::now(), events: Vec::new() }
+    }
+}
+
+impl ClientAdapter for Loopback {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.sent.push_back(frame);
+        Result::Ok(())
+    }
+
+    fn on_disconnect(&mut self, code: DisconnectCode, message: &str, rejoin: bool) {
+        self.events.push(format!("on_disconnect({}, {}, {})", code, message, rejoin));
+    }
+
+    fn on_cube_dict(&mut self, signal: &CubeDictSignal) {
+        self.events.push(format!("on_cube_dict({})", signal.defs.len()));
+    }
+
+    fn on_cube_batch(&mut self, signal: &CubeBatchSignal) {
+        self.events.push(format!("on_cube_batch({}, {}, {})", signal.pos.x, signal.pos.y, signal.pos.z));
+    }
+
+    fn on_cube_update(&mut self, signal: &CubeUpdateSignal) {
+        self.events.push(format!("on_cube_update({})", signal.cube));
+    }
+
+    fn on_flex_motion(&mut self, signal: &FlexMotionSignal) {
+        self.events.push(format!("on_flex_motion({})", signal.event_time));
+    }
+
+    fn on_flex_flags(&mut self, signal: &FlexFlagsSignal) {
+        self.events.push(format!("on_flex_flags({})", signal.crouch));
+    }
+
+    fn on_world_switch(&mut self, signal: &WorldSwitchSignal) {
+        self.events.push(format!("on_world_switch({})", signal.world));
+    }
+}
+
+impl SessionAdapter for Loopback {
+    fn send(&mut self, frame: Vec<u8>) -> VioResult {
+        self.sent.push_back(frame);
+        Result::Ok(())
+    }
+
+    fn now(&self) -> Instant { self.now }
+
+    fn on_user_motion(&mut self, signal: &UserMotionSignal) {
+        self.events.push(format!("on_user_motion({})", signal.yaw));
+    }
+
+    fn on_timeout(&mut self) {
+        self.events.push("on_timeout".to_owned());
+    }
+}
+
+/// Connects a client to a session and delivers its LOGIN_REQUEST.
+/// The response of the session is left in `session.adapter().sent`.
+pub fn handshake(client_config: ClientConfig, session_config: SessionConfig) -> (Client<Loopback>, Session<Loopback>) {
+    let mut client = Client::new(Loopback::default(), client_config);
+    let mut session = Session::with_config(Loopback::default(), session_config);
+    client.connect().unwrap();
+    session.receive(&client.adapter_mut().sent.pop_front().unwrap()).unwrap();
+    (client, session)
+}
+
+/// Completes `handshake` with LOGIN_ACCEPT, leaving both sides in the loading state
+pub fn login(client_config: ClientConfig, session_config: SessionConfig) -> (Client<Loopback>, Session<Loopback>) {
+    let (mut client, mut session) = handshake(client_config, session_config);
+    session.accept().unwrap();
+    deliver(&mut session, &mut client);
+    (client, session)
+}
+
+/// Feeds every frame sent by the session into the client
+pub fn deliver(session: &mut Session<Loopback>, client: &mut Client<Loopback>) {
+    for frame in session.adapter_mut().sent.drain(..) {
+        client.receive(&frame).unwrap();
+    }
+}
+
+/// The secret key of the replayed clients, whose user ID is `5b27aa5589179770e47575b162a1ded97b8bfc6d`
+pub const FIXTURE_SECRET: [u8; 32] = [
+    0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+    0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+];
+
+/// Challenges with the nonce of the conversation, so that the responses can be replayed
+struct FixedNonce(Vec<u8>);
+
+impl Authenticator for FixedNonce {
+    fn nonce(&self) -> IoResult<Vec<u8>> {
+        Result::Ok(self.0.clone())
+    }
+
+    fn verify(&self, user_id: &[u8; 20], nonce: &[u8], response: &AuthResponse) -> Result<(), AuthError> {
+        KeypairAuthenticator.verify(user_id, nonce, response)
+    }
+}
+
+/// The client identity used by `tests/hex/lib/login.txt`
+pub fn fixture_client_config() -> ClientConfig {
+    ClientConfig {
+        username: "Steve".to_owned(),
+        user_id: [
+            0x12, 0x34, 0x56, 0x78, 0xab, 0xcd, 0xef, 0xab, 0xcd, 0xef,
+            0x12, 0x34, 0x56, 0x78, 0xab, 0xcd, 0xef, 0xab, 0xcd, 0xef,
+        ],
+        language: "en_US".to_owned(),
+        sys_info: "{}".to_owned(),
+        version: ProtocolVersion::CURRENT,
+        capabilities: CapabilitySet::EMPTY,
+        credentials: Some(Box::new(Keypair::from_secret(&FIXTURE_SECRET))),
+        backoff: Backoff::default(),
+    }
+}
+
+/// Lists a frame field by field, followed by its raw bytes
+fn describe(frame: &[u8]) -> String {
+    format!("\n{}     ({})\n", disassemble(frame), hex::encode(frame))
+}
+
+fn expect_sent(name: &str, index: usize, sent: &mut VecDeque<Vec<u8>>, expected: &[u8]) -> VioResult {
+    match sent.pop_front() {
+        Some(frame) => if frame.as_slice() != expected {
+            io_error_f(format!("{}: step {} expected{}got{}", name, index, describe(expected), describe(&frame)))?
+        },
+        None => io_error_f(format!("{}: step {} expected{}got nothing", name, index, describe(expected)))?,
+    }
+    Result::Ok(())
+}
+
+fn expect_idle(name: &str, sent: &VecDeque<Vec<u8>>) -> VioResult {
+    if let Some(frame) = sent.front() {
+        io_error_f(format!("{}: unexpected frame{}", name, describe(frame)))?;
+    }
+    Result::Ok(())
+}
+
+/// The client identity in the first LOGIN_REQUEST of the conversation,
+/// so that includes like `+ login username=Alex` are replayed by a matching client
+fn client_config(test: &Conversation) -> IoResult<ClientConfig> {
+    let step = match test.steps.first() {
+        Some(step) if !step.from_server && step.buffer.first() == Some(&LL_LOGIN_REQUEST) => step,
+        _ => return Result::Ok(fixture_client_config()),
+    };
+    let request = LoginRequest::read(&mut CubeReader::new(&step.buffer[1..]))?;
+    Result::Ok(ClientConfig {
+        username: request.username,
+        user_id: request.user_id,
+        language: request.language,
+        sys_info: request.sys_info,
+        version: ProtocolVersion::new(request.major_protocol, request.minor_protocol),
+        capabilities: request.capabilities,
+        credentials: Some(Box::new(Keypair::from_secret(&FIXTURE_SECRET))),
+        backoff: Backoff::default(),
+    })
+}
+
+/// The server version and capabilities in the first LOGIN_ACCEPT of the conversation,
+/// and an authenticator with the nonce of the first AUTH_CHALLENGE
+fn session_config(test: &Conversation) -> IoResult<SessionConfig> {
+    let mut config = SessionConfig::default();
+    let first = |id: u8| test.steps.iter().find(|step| step.from_server && step.buffer.first() == Some(&id));
+    if let Some(step) = first(LL_LOGIN_ACCEPT) {
+        let accept = LoginAccept::read(&mut CubeReader::new(&step.buffer[1..]))?;
+        config.version = ProtocolVersion::new(ProtocolVersion::CURRENT.major, accept.minor_protocol);
+        config.capabilities = accept.capabilities;
+    }
+    if let Some(step) = first(LL_AUTH_CHALLENGE) {
+        let challenge = AuthChallenge::read(&mut CubeReader::new(&step.buffer[1..]))?;
+        config.authenticator = Some(Arc::new(FixedNonce(challenge.nonce)));
+    }
+    Result::Ok(config)
+}
+
+/// Replays the conversation against a `Client`.
+/// Server steps are fed into the client, and client steps must match what the client sent.
+pub fn replay_client(test: &Conversation) -> VioResult {
+    let mut client = Client::new(Loopback::default(), client_config(test)?);
+    client.connect()?;
+    for (i, step) in test.steps.iter().enumerate() {
+        if step.from_server {
+            expect_idle(&test.name, &client.adapter().sent)?;
+            client.receive(&step.buffer)?;
+        } else {
+            expect_sent(&test.name, i, &mut client.adapter_mut().sent, &step.buffer)?;
+        }
+    }
+    expect_idle(&test.name, &client.adapter().sent)
+}
+
+/// Replays the conversation against a `Session`.
+/// Client steps are fed into the session, and each server step is decoded
+/// and re-enacted through the session API, which must send the exact same bytes.
+/// Server steps that the session already sent on its own, e.g. a version rejection,
+/// are only compared.
+pub fn replay_server(test: &Conversation) -> VioResult {
+    let mut session = Session::with_config(Loopback::default(), session_config(test)?);
+    for (i, step) in test.steps.iter().enumerate() {
+        if step.from_server {
+            if !session.adapter().sent.is_empty() {
+                expect_sent(&test.name, i, &mut session.adapter_mut().sent, &step.buffer)?;
+                continue;
+            }
+            let mut action = ServerAction::None;
+            handle_frame(&mut action, &step.buffer)?;
+            match action {
+                ServerAction::None => io_error("Server step has no signal")?,
+                ServerAction::Accept => session.accept()?,
+                ServerAction::Disconnect(code, message, rejoin) => session.disconnect(code, message.as_str(), rejoin)?,
+            }
+            expect_sent(&test.name, i, &mut session.adapter_mut().sent, &step.buffer)?;
+        } else {
+            expect_idle(&test.name, &session.adapter().sent)?;
+            session.receive(&step.buffer)?;
+        }
+    }
+    expect_idle(&test.name, &session.adapter().sent)
+}
+
+/// The session API call corresponding to a server step
+enum ServerAction {
+    None,
+    Accept,
+    Disconnect(DisconnectCode, String, bool),
+}
+
+macro_rules! unsupported {
+    () => (
+        io_error("Signal is not supported in server steps")
+    )
+}
+
+#[allow(unused_variables)]
+impl SignalHandler for ServerAction {
+    fn handle_ll_login_request(&mut self, signal: LoginRequest) -> VioResult { unsupported!() }
+
+    fn handle_ll_auth_challenge(&mut self, signal: AuthChallenge) -> VioResult { unsupported!() }
+
+    fn handle_ll_auth_response(&mut self, signal: AuthResponse) -> VioResult { unsupported!() }
+
+    fn handle_ll_login_accept(&mut self, signal: LoginAccept) -> VioResult {
+        *self = ServerAction::Accept;
+        Result::Ok(())
+    }
+
+    fn handle_ll_server_disconnect(&mut self, signal: ServerDisconnect) -> VioResult {
+        *self = ServerAction::Disconnect(DisconnectCode::Custom, signal.reason, signal.rejoin);
+        Result::Ok(())
+    }
+
+    fn handle_ll_server_disconnect_reason(&mut self, signal: ServerDisconnectReason) -> VioResult {
+        *self = ServerAction::Disconnect(signal.code, signal.message, signal.rejoin);
+        Result::Ok(())
+    }
+
+    fn handle_ll_client_disconnect(&mut self, signal: ClientDisconnect) -> VioResult { unsupported!() }
+
+    fn handle_ll_ping(&mut self, signal: Ping) -> VioResult { unsupported!() }
+
+    fn handle_ll_pong(&mut self, signal: Pong) -> VioResult { unsupported!() }
+
+    fn handle_pk_spawn(&mut self, signal: SpawnSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_cube_batch(&mut self, signal: CubeBatchSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_cube_dict(&mut self, signal: CubeDictSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_flex_motion(&mut self, signal: FlexMotionSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_cube_update(&mut self, signal: CubeUpdateSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_cube_interact(&mut self, signal: CubeInteractSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_user_motion(&mut self, signal: UserMotionSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_user_flags(&mut self, signal: UserFlagsSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_flex_flags(&mut self, signal: FlexFlagsSignal) -> VioResult { unsupported!() }
+
+    fn handle_pk_world_switch(&mut self, signal: WorldSwitchSignal) -> VioResult { unsupported!() }
+}
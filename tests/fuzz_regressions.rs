@@ -0,0 +1,162 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+
+//! Minimised crashes found by the targets in `fuzz/`
+
+extern crate cube_engine;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Read;
+use std::ptr;
+
+use cube_engine::conformance::{disassemble, lex_step_line, parse_step};
+use cube_engine::io::reader::CubeReader;
+use cube_engine::protocol::ll::handle_ll;
+use cube_engine::protocol::ll::package::{handle_package, handle_package_slice, LL_PACKAGE};
+use cube_engine::server::{Session, SessionConfig};
+
+mod common;
+
+/// Fails allocations larger than 64 MiB, like the `-malloc_limit_mb` of the fuzzer,
+/// so that lazily zeroed allocations of untrusted sizes do not pass silently.
+/// A failed allocation aborts the test process through `handle_alloc_error`,
+/// since a global allocator must not unwind.
+struct LimitedAllocator;
+
+const ALLOCATION_LIMIT: usize = 64 << 20;
+
+unsafe impl GlobalAlloc for LimitedAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() > ALLOCATION_LIMIT {
+            return ptr::null_mut();
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if layout.size() > ALLOCATION_LIMIT {
+            return ptr::null_mut();
+        }
+        System.alloc_zeroed(layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: LimitedAllocator = LimitedAllocator;
+
+fn session() -> Session<common::replay::Loopback> {
+    Session::new(common::replay::Loopback::default())
+}
+
+/// Deflates `pattern` repeated to at least `size` bytes as a single block with fixed Huffman codes,
+/// which is much faster than compressing the repetitions.
+/// The pattern must be at most 4 bytes of values below 144, which have 8-bit literal codes.
+fn deflate_repeated(pattern: &[u8], size: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut bits = 0usize;
+    let mut push = |value: u32, count: usize, msb_first: bool| {
+        for i in 0..count {
+            let bit = if msb_first { (value >> (count - 1 - i)) & 1 } else { (value >> i) & 1 };
+            if bits.is_multiple_of(8) {
+                bytes.push(0);
+            }
+            *bytes.last_mut().unwrap() |= (bit as u8) << (bits % 8);
+            bits += 1;
+        }
+    };
+    push(1, 1, false); // BFINAL
+    push(1, 2, false); // BTYPE = fixed Huffman codes
+    for &byte in pattern {
+        push(0x30 + byte as u32, 8, true);
+    }
+    for _ in 0..(size - pattern.len()).div_ceil(258) {
+        push(0xc5, 8, true); // length 258
+        push(pattern.len() as u32 - 1, 5, true); // distance of the pattern length
+    }
+    push(0, 7, true); // end of block
+    bytes
+}
+
+/// An LL_PACKAGE whose length exceeds the frame was allocated in full before reading
+#[test]
+fn ll_package_length() {
+    let frame = [0xe1, 0x60, 0x60, 0x60, 0x77];
+    assert!(handle_ll(&mut session(), &mut CubeReader::new(&frame[..])).is_err());
+    assert!(session().receive(&frame).is_err());
+}
+
+#[test]
+fn package_length() {
+    let package = [0x80, 0x00, 0x00, 0x00];
+    assert!(handle_package(&mut session(), &mut CubeReader::new(&package[..])).is_err());
+}
+
+#[test]
+fn string32_length() {
+    let buffer = [0xff, 0xff, 0xff, 0xff, 0x41];
+    assert!(CubeReader::new(&buffer[..]).read_string32().is_err());
+    assert!(CubeReader::new(&buffer[..]).read_str32().is_err());
+}
+
+/// libflate 0.1 panicked when deflating some incompressible data, such as a nested `Z{...}` section
+#[test]
+fn deflate_incompressible() {
+    let hex = "d04b1a010000701e77a9c9ec70b899d331a66bd59ad284a2b212822ca21720bacab27492460fec013dec4391d2eaca0a833e6805695a111411f5212ba888de5041ffc18f40d86049a128f96c8c6f804ca9cd8310d77410ad9e857f0a02b0fe2e9d1996029fa85a1608884050ce00c07237e9f47219ebe1253d879ca4c8b3ca3b4384a25e600b1f391ec8b2e68e736539741d1f522d541f511f549b632a85349b6e58092e3ebdbcdac79d4fc137c4a25532dd47018829188b7a0508fdd9cd7a7db7a5901e160f4fb2312b641a326196afbbff4489b4740dea25442c3b4992ae101471c93969b51f02aef3c5c4fdfcf2d627ed301c6fa8538396717345caffcede1adf45a327d487308b4e24ab4b0b829581ae092a8ab89defdb3eb60c5e9e4cc618b57b3fd80011faf6c02b68d27303a35d0cda9fdfe2798f32aed1830c0d0b2137533c06a2a525888c856836e452b2277200a96ce3b66c2ed9073bc842875ad0ea6366646eef307f2daec577f38def74bae79bf33d4ddd4c647beed4b3c3d7614e88c079497efd7f6dca6f475f14a38fd043d48490d7634083282f";
+    let mut tokens = Vec::new();
+    lex_step_line(format!("Z{{ {} }}", hex).as_str(), &mut tokens).unwrap();
+    let deflated = parse_step(&tokens).unwrap();
+
+    let mut inflated = Vec::new();
+    libflate::deflate::Decoder::new(deflated.as_slice()).read_to_end(&mut inflated).unwrap();
+    assert_eq!(hex::encode(inflated), hex);
+}
+
+/// A 500 KiB package of USER_FLAGS signals that inflates to 80 MiB, past both `MAX_PACKAGE_INFLATED` and the allocation limit
+#[test]
+fn package_bomb() {
+    let pattern = [0x80, 0x03, 0x13, 0x00];
+    let mut inflated = Vec::new();
+    libflate::deflate::Decoder::new(deflate_repeated(&pattern, 1000).as_slice()).read_to_end(&mut inflated).unwrap();
+    assert_eq!(inflated, pattern.repeat(inflated.len() / 4));
+    assert!(inflated.len() >= 1000);
+
+    let deflated = deflate_repeated(&pattern, 80 << 20);
+    let mut package = (deflated.len() as u32).to_be_bytes().to_vec();
+    package.extend(deflated);
+
+    let (_, mut session) = common::replay::login(common::replay::fixture_client_config(), SessionConfig::default());
+    // read_to_end also fails at the allocation limit, so check that the cap stopped it first
+    let err = handle_package_slice(&mut session, &mut CubeReader::new(&package[..])).unwrap_err();
+    assert!(err.to_string().contains("MAX_PACKAGE_INFLATED"), "{}", err);
+    assert!(handle_package(&mut session, &mut CubeReader::new(&package[..])).is_err());
+    package.insert(0, LL_PACKAGE);
+    let err = session.receive(&package).unwrap_err();
+    assert!(err.to_string().contains("MAX_PACKAGE_INFLATED"), "{}", err);
+
+    let disassembly = disassemble(&package);
+    let names: Vec<&str> = disassembly.annotations.iter().map(|annotation| annotation.name.as_str()).collect();
+    assert_eq!(names[..2], ["LL_PACKAGE", "length"]);
+    assert!(names[2].starts_with("deflated (") && names[2].contains("MAX_PACKAGE_INFLATED"), "{}", names[2]);
+    assert_eq!(parse_step(&disassembly.tokens).unwrap(), package);
+}
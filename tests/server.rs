@@ -0,0 +1,367 @@
+/*
+ * cube-engine
+ *
+ * Copyright (C) 2019 SOFe
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+extern crate cube_engine;
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use cube_engine::auth::{Keypair, KeypairAuthenticator};
+use cube_engine::client::{Client, ClientConfig, ClientState};
+use cube_engine::io::cube::{FloatPos, IntPos};
+use cube_engine::io::flex::FlexPos;
+use cube_engine::io::reader::CubeReader;
+use cube_engine::protocol::capability::CapabilitySet;
+use cube_engine::protocol::heartbeat::HeartbeatConfig;
+use cube_engine::protocol::ll::disconnect::{DisconnectCode, LL_SERVER_DISCONNECT_REASON, ServerDisconnectReason};
+use cube_engine::protocol::ll::login_accept::LL_LOGIN_ACCEPT;
+use cube_engine::protocol::ll::login_request::LoginRequest;
+use cube_engine::protocol::ll::package::PackageWriter;
+use cube_engine::protocol::ll::ping::{LL_PING, Pong};
+use cube_engine::protocol::handler::SignalHandler;
+use cube_engine::protocol::pk::cube_batch::CubeBatchSignal;
+use cube_engine::protocol::pk::user_motion::UserMotionSignal;
+use cube_engine::protocol::sys_info::SysInfo;
+use cube_engine::protocol::version::ProtocolVersion;
+use cube_engine::protocol::write_frame;
+use cube_engine::server::{BanList, Catalog, LoginDecision, LoginPolicy, MaxPlayers, ResumptionStore, Session, SessionConfig, SessionState};
+use cube_engine::text::Text;
+
+use common::replay::{deliver, fixture_client_config, handshake, login, Loopback};
+
+#[test]
+fn test_hex() {
+    let tests = common::read_tests();
+    assert!(!tests.is_empty());
+    for test in tests {
+        if let Err(err) = common::replay::replay_server(&test) {
+            panic!("Test \"{}\" failed: {}", test.name, err);
+        }
+    }
+}
+
+#[test]
+fn negotiate_capabilities() {
+    // no capabilities are defined yet, but unknown bits are still negotiated
+    let both = CapabilitySet::from_bits(1);
+    for &(client_capabilities, server_capabilities, server_minor, expected) in &[
+        (both, both, ProtocolVersion::CURRENT.minor, true),
+        (both, CapabilitySet::EMPTY, ProtocolVersion::CURRENT.minor, false),
+        (CapabilitySet::EMPTY, both, ProtocolVersion::CURRENT.minor, false),
+        (both, both, 1, false),
+    ] {
+        let version = ProtocolVersion::new(ProtocolVersion::CURRENT.major, server_minor);
+        let (client, session) = login(
+            ClientConfig { capabilities: client_capabilities, ..fixture_client_config() },
+            SessionConfig { version, capabilities: server_capabilities, ..SessionConfig::default() },
+        );
+
+        assert_eq!(session.capabilities().bits() == 1, expected);
+        assert_eq!(client.capabilities().bits() == 1, expected);
+        assert_eq!(client.version(), Some(version));
+    }
+}
+
+#[test]
+fn authenticate() {
+    let keypair = Keypair::generate().unwrap();
+    for &(user_id, expected) in &[(keypair.user_id(), true), ([0; 20], false)] {
+        let credentials = Keypair::from_secret(&keypair.secret());
+        let (mut client, mut session) = handshake(
+            ClientConfig { user_id, credentials: Some(Box::new(credentials)), ..fixture_client_config() },
+            SessionConfig { authenticator: Some(Arc::new(KeypairAuthenticator)), ..SessionConfig::default() },
+        );
+
+        assert_eq!(session.state(), SessionState::Authenticating);
+        client.receive(&session.adapter_mut().sent.pop_front().unwrap()).unwrap();
+        assert_eq!(client.state(), ClientState::Authenticating);
+        session.receive(&client.adapter_mut().sent.pop_front().unwrap()).unwrap();
+
+        assert_eq!(session.is_authenticated(), expected);
+        let expected_state = if expected { SessionState::LoginRequested } else { SessionState::Disconnected };
+        assert_eq!(session.state(), expected_state);
+    }
+}
+
+#[test]
+fn login_policies() {
+    let bans = Arc::new(BanList::new());
+    bans.ban([1; 20], "Banned for griefing");
+    let max_players = Arc::new(MaxPlayers::new(1));
+    let policies: Vec<Arc<dyn LoginPolicy>> = vec![bans, max_players.clone()];
+    let login = |user_id: [u8; 20]| {
+        let config = SessionConfig { login_policies: policies.clone(), ..SessionConfig::default() };
+        handshake(ClientConfig { user_id, ..fixture_client_config() }, config).1
+    };
+    let disconnect = |session: &mut Session<Loopback>| {
+        let frame = session.adapter_mut().sent.pop_front().unwrap();
+        assert_eq!(frame[0], LL_SERVER_DISCONNECT_REASON);
+        let signal = ServerDisconnectReason::read(&mut CubeReader::new(&frame[1..])).unwrap();
+        (signal.code, signal.message, signal.rejoin)
+    };
+
+    let mut banned = login([1; 20]);
+    assert_eq!(banned.state(), SessionState::Disconnected);
+    assert_eq!(disconnect(&mut banned), (DisconnectCode::Banned, "Banned for griefing".to_owned(), false));
+
+    let mut first = login([2; 20]);
+    assert_eq!(first.state(), SessionState::Loading);
+    assert_eq!(first.adapter_mut().sent.pop_front().unwrap()[0], LL_LOGIN_ACCEPT);
+    assert_eq!(max_players.online(), 1);
+
+    let mut second = login([3; 20]);
+    assert_eq!(disconnect(&mut second), (DisconnectCode::Custom, "The server is full".to_owned(), true));
+
+    first.disconnect(DisconnectCode::Kicked, "Bye", false).unwrap();
+    assert_eq!(max_players.online(), 0);
+    assert_eq!(login([3; 20]).state(), SessionState::Loading);
+}
+
+#[test]
+fn deferred_login_policies() {
+    let max_players = Arc::new(MaxPlayers::new(1));
+    let bans = Arc::new(BanList::new());
+    let defer = |_: &LoginRequest| LoginDecision::Defer;
+    let policies: Vec<Arc<dyn LoginPolicy>> = vec![max_players.clone(), bans.clone(), Arc::new(defer)];
+    let login = |user_id: [u8; 20]| {
+        let config = SessionConfig { login_policies: policies.clone(), ..SessionConfig::default() };
+        handshake(ClientConfig { user_id, ..fixture_client_config() }, config).1
+    };
+    let message = |session: &mut Session<Loopback>| {
+        let frame = session.adapter_mut().sent.pop_back().unwrap();
+        ServerDisconnectReason::read(&mut CubeReader::new(&frame[1..])).unwrap().message
+    };
+
+    // a deferred login holds its slot, so a second login is rejected at once
+    let mut first = login([1; 20]);
+    assert_eq!(first.state(), SessionState::LoginRequested);
+    assert_eq!(max_players.online(), 1);
+    let mut second = login([2; 20]);
+    assert_eq!(second.state(), SessionState::Disconnected);
+    assert_eq!(message(&mut second), "The server is full");
+    assert_eq!(max_players.online(), 1);
+
+    // the policies are checked again on accept, releasing the slot if the login is rejected by then
+    bans.ban([1; 20], "Banned while waiting");
+    assert!(first.accept().is_err());
+    assert_eq!(first.state(), SessionState::Disconnected);
+    assert_eq!(message(&mut first), "Banned while waiting");
+    assert_eq!(max_players.online(), 0);
+
+    let mut third = login([3; 20]);
+    third.close();
+    assert_eq!(max_players.online(), 0);
+
+    let mut fourth = login([4; 20]);
+    fourth.accept().unwrap();
+    assert_eq!(fourth.state(), SessionState::Loading);
+    assert_eq!(max_players.online(), 1);
+    fourth.disconnect(DisconnectCode::Kicked, "Bye", false).unwrap();
+    assert_eq!(max_players.online(), 0);
+}
+
+#[test]
+fn reject_package_before_login() {
+    let frame = write_frame(|writer| PackageWriter::new().flush(writer)).unwrap();
+    let (_, mut session) = handshake(fixture_client_config(), SessionConfig::default());
+    assert_eq!(session.state(), SessionState::LoginRequested);
+    assert!(session.receive(&frame).is_err());
+
+    let (_, mut session) = login(fixture_client_config(), SessionConfig::default());
+    session.receive(&frame).unwrap();
+}
+
+#[test]
+fn reject_game_play_before_login() {
+    let motion = UserMotionSignal { yaw: 1.5, dash: false };
+    let frame = write_frame(|writer| {
+        let mut package = PackageWriter::new();
+        package.write(|writer| motion.write(writer))?;
+        package.flush(writer)
+    }).unwrap();
+
+    let (_, mut session) = handshake(fixture_client_config(), SessionConfig::default());
+    assert!(session.receive(&frame).is_err());
+    assert!(session.handle_pk_user_motion(motion.clone()).is_err());
+    assert!(session.adapter().events.is_empty());
+
+    let (_, mut session) = login(fixture_client_config(), SessionConfig::default());
+    session.receive(&frame).unwrap();
+    assert_eq!(session.adapter().events, vec!["on_user_motion(1.5)"]);
+}
+
+#[test]
+fn sys_info() {
+    assert_eq!(Session::new(Loopback::default()).sys_info(), None);
+    let info = SysInfo { client_name: Some("cube-web".to_owned()), render_distance: Some(4), ..SysInfo::default() };
+    for (sys_info, expected) in [(info.to_json(), Some(&info)), ("not json".to_owned(), None)] {
+        let (_, session) = handshake(ClientConfig { sys_info, ..fixture_client_config() }, SessionConfig::default());
+        assert_eq!(session.sys_info(), expected);
+    }
+}
+
+#[test]
+fn heartbeat() {
+    let second = Duration::from_secs(1);
+    let config = SessionConfig { heartbeat: HeartbeatConfig { interval: second, max_missed: 2 }, ..SessionConfig::default() };
+    let (_, mut session) = handshake(fixture_client_config(), config);
+    session.tick().unwrap();
+    assert!(session.adapter().sent.is_empty(), "no PING before LOGIN_ACCEPT");
+    session.accept().unwrap();
+    session.adapter_mut().sent.clear();
+
+    session.tick().unwrap();
+    assert_eq!(session.adapter_mut().sent.pop_front().unwrap()[0], LL_PING);
+    session.adapter_mut().now += Duration::from_millis(50);
+    session.receive(&write_frame(|writer| Pong {}.write(writer)).unwrap()).unwrap();
+    assert_eq!(session.heartbeat().rtt(), Some(Duration::from_millis(50)));
+
+    for _ in 0..2 {
+        session.adapter_mut().now += second;
+        session.tick().unwrap();
+        assert_eq!(session.adapter_mut().sent.pop_front().unwrap()[0], LL_PING);
+    }
+    session.adapter_mut().now += second;
+    session.tick().unwrap();
+    assert_eq!(session.adapter_mut().sent.pop_front().unwrap()[0], LL_SERVER_DISCONNECT_REASON);
+    assert_eq!(session.state(), SessionState::Disconnected);
+    assert_eq!(session.adapter().events, vec!["on_timeout"]);
+}
+
+#[test]
+fn resume_session() {
+    let store = Arc::new(ResumptionStore::new(Duration::from_secs(30)));
+    let config = || SessionConfig { resumption: Some(store.clone()), ..SessionConfig::default() };
+    let rejoin = |client: &mut Client<Loopback>, after: Duration, start| {
+        let mut session = Session::with_config(Loopback { now: start + after, ..Loopback::default() }, config());
+        client.reconnect().unwrap();
+        session.receive(&client.adapter_mut().sent.pop_front().unwrap()).unwrap();
+        session.accept().unwrap();
+        deliver(&mut session, client);
+        session
+    };
+    let position = FlexPos::from_world(FloatPos { x: 8.0, y: 1.0, z: 8.0 }, 0.0, 0.0);
+    let batch = CubeBatchSignal { pos: IntPos { x: 0, y: 0, z: 0 }, payload: [0; 4096] };
+
+    let (mut client, mut session) = login(fixture_client_config(), config());
+    let start = session.adapter().now;
+    assert!(!session.is_resumed() && !client.is_resumed());
+    session.send_batch(&batch).unwrap();
+    session.spawn(position).unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.state(), ClientState::Spawned);
+
+    session.close();
+    client.connection_lost();
+    assert_eq!(store.len(start), 1);
+    assert_eq!(client.rejoin_delay(), Some(Duration::from_secs(1)));
+
+    let mut resumed = rejoin(&mut client, Duration::from_secs(10), start);
+    assert!(resumed.is_resumed() && client.is_resumed());
+    assert_eq!(resumed.state(), SessionState::Spawned);
+    assert_eq!(client.state(), ClientState::Spawned);
+    assert_eq!(resumed.position(), Some(&position));
+    assert!(resumed.loaded_batches().contains(&batch.pos));
+    assert!(store.is_empty(start));
+
+    resumed.disconnect(DisconnectCode::ServerShutdown, "Restarting", true).unwrap();
+    deliver(&mut resumed, &mut client);
+    assert_eq!(store.len(start), 1);
+
+    let (_, other) = login(ClientConfig { user_id: [1; 20], ..fixture_client_config() }, config());
+    assert!(!other.is_resumed());
+
+    let expired = rejoin(&mut client, Duration::from_secs(60), start);
+    assert!(!expired.is_resumed() && !client.is_resumed());
+    assert_eq!(expired.state(), SessionState::Loading);
+    assert_eq!(client.state(), ClientState::Loading);
+    assert!(expired.loaded_batches().is_empty());
+}
+
+#[test]
+fn disconnect_codes() {
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig::default());
+    let request = session.login_request().unwrap().clone();
+
+    assert!(session.receive(&write_frame(|writer| request.write(writer)).unwrap()).is_err());
+    assert_eq!(session.state(), SessionState::Disconnected);
+    deliver(&mut session, &mut client);
+    let expected = format!("on_disconnect({}, Received duplicate LOGIN_REQUEST, false)", DisconnectCode::ProtocolViolation);
+    assert_eq!(client.adapter().events, vec![expected]);
+}
+
+#[test]
+fn translate() {
+    let mut catalog = Catalog::new("en");
+    catalog.insert_json("de", r#"{"login.serverFull": "Der Server ist voll", "kick": "{0} hat dich entfernt"}"#).unwrap();
+    let catalog = Arc::new(catalog);
+    let login = |language: &str, max_players: usize| {
+        let config = SessionConfig { catalog: Some(catalog.clone()), login_policies: vec![Arc::new(MaxPlayers::new(max_players))], ..SessionConfig::default() };
+        handshake(ClientConfig { language: language.to_owned(), ..fixture_client_config() }, config).1
+    };
+    let message = |session: &mut Session<Loopback>| {
+        let frame = session.adapter_mut().sent.pop_back().unwrap();
+        ServerDisconnectReason::read(&mut CubeReader::new(&frame[1..])).unwrap().message
+    };
+
+    assert_eq!(message(&mut login("de_DE", 0)), "Der Server ist voll");
+    assert_eq!(message(&mut login("fr_FR", 0)), "The server is full");
+
+    let mut session = login("de_AT", 1);
+    assert_eq!(session.language(), Some("de_AT"));
+    let kick = Text::translatable("kick", vec!["Alex".to_owned()], "{0} kicked you");
+    assert_eq!(session.translate(&kick), "Alex hat dich entfernt");
+    session.disconnect(DisconnectCode::Kicked, kick, false).unwrap();
+    assert_eq!(message(&mut session), "Alex hat dich entfernt");
+}
+
+#[test]
+fn switch_world() {
+    let position = FlexPos::from_world(FloatPos { x: 8.0, y: 1.0, z: 8.0 }, 0.0, 0.0);
+    let batch = CubeBatchSignal { pos: IntPos { x: 0, y: 0, z: 0 }, payload: [0; 4096] };
+
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig::default());
+    assert!(session.switch_world("nether").is_err());
+    session.send_batch(&batch).unwrap();
+    session.spawn(position).unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.state(), ClientState::Spawned);
+    client.adapter_mut().events.clear();
+
+    session.switch_world("nether").unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(session.state(), SessionState::Loading);
+    assert_eq!(session.world(), Some("nether"));
+    assert!(session.loaded_batches().is_empty());
+    assert_eq!(session.position(), None);
+    assert_eq!(client.state(), ClientState::Loading);
+    assert_eq!(client.adapter().events, vec!["on_world_switch(nether)"]);
+
+    session.spawn(position).unwrap();
+    deliver(&mut session, &mut client);
+    assert_eq!(client.state(), ClientState::Spawned);
+
+    let (mut client, mut session) = login(fixture_client_config(), SessionConfig { version: ProtocolVersion::new(1, 5), ..SessionConfig::default() });
+    session.spawn(position).unwrap();
+    deliver(&mut session, &mut client);
+    assert!(session.switch_world("nether").is_err());
+    assert_eq!(session.state(), SessionState::Spawned);
+}